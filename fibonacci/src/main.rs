@@ -1,11 +1,119 @@
-use core::cell::RefCell;
-use std::rc::Rc;
-use wasmi::{ImportsBuilder, ModuleInstance, NopExternals, RuntimeValue};
+use std::time::Instant;
 
+use halo2_proofs::pairing::bn256::Fr;
+use zkswavm::circuits::event::CircuitOptions;
+use zkswavm::specs::types::Value;
+use zkswavm::{
+    prove, public_inputs, setup, setup_params, verify, WasmCircuit, WasmInterpreter, WasmRuntime,
+};
+
+/// Program the circuits support, the fibonacci of the tests is rejected at
+/// compile time until branches, calls and arithmetic have circuits.
+const ECHO: &str = r#"
+        (module
+            (func $echo (export "echo") (param $0 i32) (result i32)
+             (i32.const 1)
+             (drop)
+             (local.get $0)
+             (return)
+            )
+           )
+        "#;
+
+/// Proves and verifies that `entry` of `program` returns `expected` when
+/// called with `args`, which are the public inputs and outputs, printing timings
+/// and sizes.
+fn prove_program(
+    program: &str,
+    entry: &str,
+    args: Vec<Value>,
+    expected: Vec<Value>,
+) -> Result<Vec<Value>, String> {
+    let runtime = WasmInterpreter::new();
+    let compiled_module = runtime
+        .compile(program, entry)
+        .map_err(|err| format!("failed to compile: {}", err))?;
+
+    let timer = Instant::now();
+    let execution_log = runtime
+        .run(&compiled_module, args.clone())
+        .map_err(|err| format!("failed to execute export: {}", err))?;
+    if execution_log.returns != expected {
+        return Err(format!(
+            "{} returns {:?}, expected {:?}",
+            entry, execution_log.returns, expected
+        ));
+    }
+    println!("{}({:?}) = {:?}", entry, args, expected);
+    println!(
+        "execution: {:?}, {} events, {} memory entries",
+        timer.elapsed(),
+        execution_log.tables.event.len(),
+        execution_log.tables.memory.entries().len()
+    );
+
+    let options = CircuitOptions::of(&compiled_module.tables);
+    let k = WasmCircuit::<Fr>::new(
+        compiled_module.tables.clone(),
+        execution_log.tables.clone(),
+        options.clone(),
+    )
+    .min_k()
+    .map_err(|err| err.to_string())?;
+
+    let timer = Instant::now();
+    let params = setup_params(k);
+    let pk = setup(&params, &options, &compiled_module.tables)
+        .map_err(|err| format!("failed to generate keys: {}", err))?;
+    println!("setup: {:?}, k = {}", timer.elapsed(), k);
+
+    let timer = Instant::now();
+    let proof = prove(
+        &params,
+        &pk,
+        &options,
+        compiled_module.tables.clone(),
+        execution_log.tables,
+    )
+    .map_err(|err| format!("failed to prove: {}", err))?;
+    println!(
+        "prove: {:?}, proof of {} bytes",
+        timer.elapsed(),
+        proof.transcript.len()
+    );
+
+    // the verifier only knows the image, the arguments and the claimed result
+    if proof.instances != public_inputs(&compiled_module.tables, &args, &expected, None) {
+        return Err("the proof is not about the claimed execution".to_string());
+    }
+    let timer = Instant::now();
+    verify(&params, pk.get_vk(), &proof).map_err(|err| format!("failed to verify: {:?}", err))?;
+    println!("verify: {:?}", timer.elapsed());
+
+    Ok(expected)
+}
+
+/// e.g. `cargo run --release -- 10`
 fn main() {
-    // Parse WAT (WebAssembly Text format) into wasm bytecode.
-    let wasm_binary: Vec<u8> = wabt::wat2wasm(
-        r#"
+    let n: i32 = std::env::args()
+        .nth(1)
+        .map(|n| n.parse().expect("n must be an i32"))
+        .unwrap_or(6);
+
+    if let Err(err) = prove_program(ECHO, "echo", vec![Value::I32(n)], vec![Value::I32(n)]) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zkswavm::specs::types::{CompileError, Value};
+    use zkswavm::{WasmInterpreter, WasmRuntime};
+
+    use crate::{prove_program, ECHO};
+
+    const FIBONACCI: &str = r#"
             (module
                 (memory $0 1)
                 (export "memory" (memory $0))
@@ -41,38 +149,46 @@ fn main() {
                  )
                 )
                )
-            "#,
-    )
-    .expect("failed to parse wat");
-
-    let mut tracer = wasmi::tracer::Tracer::default();
+            "#;
 
-    // Load wasm binary and prepare it for instantiation.
-    let module = wasmi::Module::from_buffer(&wasm_binary).expect("failed to load wasm");
+    fn fibonacci(n: u32) -> u32 {
+        (0..n)
+            .fold((0u32, 1u32), |(a, b), _| (b, a.wrapping_add(b)))
+            .0
+    }
 
-    // Instantiate a module with empty imports and
-    // asserting that there is no `start` function.
-    let instance = ModuleInstance::new(&module, &ImportsBuilder::default())
-        .expect("failed to instantiate wasm module")
-        .assert_no_start();
+    #[test]
+    fn test_prove_echo() {
+        let returns = vec![Value::I32(-7)];
+        assert_eq!(
+            prove_program(ECHO, "echo", returns.clone(), returns.clone()),
+            Ok(returns)
+        );
+        assert!(prove_program(ECHO, "echo", vec![Value::I32(1)], vec![Value::I32(2)]).is_err());
+    }
 
-    tracer.register_module_instance(&instance);
-    let tracer = Rc::new(RefCell::new(tracer));
+    #[test]
+    fn test_unsupported_opcodes() {
+        // the body opens with a `block`, which has no circuit yet
+        let compiler = WasmInterpreter::new();
+        assert!(matches!(
+            compiler.compile(FIBONACCI, "fibonacci"),
+            Err(CompileError::UnsupportedInstruction { fid: 0, iid: 0, .. })
+        ));
+    }
 
-    // Finally, invoke exported function "test" with no parameters
-    // and empty external function executor.
-    assert_eq!(
-        instance
-            .invoke_export_trace(
+    #[test]
+    #[ignore = "block, br_if, call and the i32 arithmetic have no circuits yet"]
+    fn test_prove_fibonacci() {
+        let result = Value::I32(fibonacci(6) as i32);
+        assert_eq!(
+            prove_program(
+                FIBONACCI,
                 "fibonacci",
-                &[wasmi::RuntimeValue::I32(6)],
-                &mut NopExternals,
-                tracer.clone(),
-            )
-            .expect("failed to execute export"),
-        Some(RuntimeValue::I32(8)),
-    );
-
-    // println!("{:?}", tracer);
-    println!("{:?}", (*tracer).borrow().etable);
+                vec![Value::I32(6)],
+                vec![result.clone()]
+            ),
+            Ok(vec![result])
+        );
+    }
 }
@@ -0,0 +1,298 @@
+use std::fmt;
+
+use crate::itable::Opcode;
+use crate::mtable::{AccessType, LocationType, MemoryTableEntry};
+use crate::{CompileTable, ExecutionTable};
+
+/// A rule of the circuits broken by an execution table.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CheckError {
+    /// The event `eid` executes an instruction missing from the instruction table.
+    MissingInstruction { eid: u64 },
+    /// The event after `previous` has `eid` instead of `previous + 1`.
+    EidNotIncreasing { previous: u64, eid: u64 },
+    /// The event after `eid` starts with a stack pointer other than `expected`.
+    SpMismatch { eid: u64, expected: u64, found: u64 },
+    /// The last event, `eid`, is not a `Return`, `None` when there is no event.
+    MissingReturn { eid: Option<u64> },
+    /// The final `Return` at `eid` leaves sp `found` instead of its kept values.
+    UnbalancedReturn { eid: u64, expected: u64, found: u64 },
+    /// The event `eid` has `found` memory operations instead of `expected`.
+    MopsMismatch { eid: u64, expected: u64, found: u64 },
+    /// The first access of a stack location is a read.
+    StackReadBeforeWrite { eid: u64, emid: u64 },
+    /// A read does not see the value or the type of the previous access.
+    ReadMismatch { eid: u64, emid: u64 },
+}
+
+impl fmt::Display for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckError::MissingInstruction { eid } => {
+                write!(f, "eid {}: instruction not in the instruction table", eid)
+            }
+            CheckError::EidNotIncreasing { previous, eid } => {
+                write!(f, "eid {}: follows eid {}", eid, previous)
+            }
+            CheckError::SpMismatch {
+                eid,
+                expected,
+                found,
+            } => write!(
+                f,
+                "eid {}: leaves sp {} but the next event has sp {}",
+                eid, expected, found
+            ),
+            CheckError::MissingReturn { eid: Some(eid) } => {
+                write!(f, "eid {}: the execution does not end with a return", eid)
+            }
+            CheckError::MissingReturn { eid: None } => write!(f, "the execution has no event"),
+            CheckError::UnbalancedReturn {
+                eid,
+                expected,
+                found,
+            } => write!(
+                f,
+                "eid {}: the final return leaves sp {} instead of {}",
+                eid, found, expected
+            ),
+            CheckError::MopsMismatch {
+                eid,
+                expected,
+                found,
+            } => write!(
+                f,
+                "eid {}: {} memory operations, the opcode takes {}",
+                eid, found, expected
+            ),
+            CheckError::StackReadBeforeWrite { eid, emid } => write!(
+                f,
+                "eid {} emid {}: stack location read before it is written",
+                eid, emid
+            ),
+            CheckError::ReadMismatch { eid, emid } => write!(
+                f,
+                "eid {} emid {}: read differs from the previous access",
+                eid, emid
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CheckError {}
+
+/// Replays the rules the circuits enforce on `execution_table`, so that an
+/// inconsistent trace is reported before it reaches the prover.
+///
+/// Memory operations are counted per event, which is stricter than the
+/// circuits comparing the totals only.
+pub fn check_execution(
+    compile_table: &CompileTable,
+    execution_table: &ExecutionTable,
+) -> Result<(), CheckError> {
+    for event in execution_table.event.iter() {
+        if !compile_table.instructions.contains(&event.inst) {
+            return Err(CheckError::MissingInstruction { eid: event.eid });
+        }
+    }
+
+    for pair in execution_table.event.windows(2) {
+        let (event, next) = (&pair[0], &pair[1]);
+
+        if next.eid != event.eid + 1 {
+            return Err(CheckError::EidNotIncreasing {
+                previous: event.eid,
+                eid: next.eid,
+            });
+        }
+
+        let expected = (event.sp as i64 + event.inst.opcode.sp_diff()) as u64;
+        if next.sp != expected {
+            return Err(CheckError::SpMismatch {
+                eid: event.eid,
+                expected,
+                found: next.sp,
+            });
+        }
+    }
+
+    // the execution halts on a return leaving only the kept values on the stack
+    let last = execution_table
+        .event
+        .last()
+        .ok_or(CheckError::MissingReturn { eid: None })?;
+    let Opcode::Return { keep, .. } = &last.inst.opcode else {
+        return Err(CheckError::MissingReturn {
+            eid: Some(last.eid),
+        });
+    };
+    let found = (last.sp as i64 + last.inst.opcode.sp_diff()) as u64;
+    if found != keep.len() as u64 {
+        return Err(CheckError::UnbalancedReturn {
+            eid: last.eid,
+            expected: keep.len() as u64,
+            found,
+        });
+    }
+
+    let entries = execution_table.memory.entries();
+    for event in execution_table.event.iter() {
+        let found = entries
+            .iter()
+            .filter(|entry| entry.eid == event.eid && entry.atype != AccessType::Init)
+            .count() as u64;
+        let expected = event.inst.opcode.mops();
+        if found != expected {
+            return Err(CheckError::MopsMismatch {
+                eid: event.eid,
+                expected,
+                found,
+            });
+        }
+    }
+
+    let mut memory = execution_table.memory.clone();
+    memory.sort();
+    let mut previous: Option<&MemoryTableEntry> = None;
+    for entry in memory.entries() {
+        match previous {
+            Some(previous) if previous.is_same_location(entry) => {
+                if entry.atype == AccessType::Read
+                    && (entry.value != previous.value || entry.vtype != previous.vtype)
+                {
+                    return Err(CheckError::ReadMismatch {
+                        eid: entry.eid,
+                        emid: entry.emid,
+                    });
+                }
+            }
+            _ => {
+                if entry.ltype == LocationType::Stack && entry.atype == AccessType::Read {
+                    return Err(CheckError::StackReadBeforeWrite {
+                        eid: entry.eid,
+                        emid: entry.emid,
+                    });
+                }
+            }
+        }
+        previous = Some(entry);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checker::{check_execution, CheckError};
+    use crate::fixture::tables;
+    use crate::itable::Opcode;
+    use crate::mtable::{AccessType, MTable, MemoryTableEntry};
+    use crate::types::ValueType;
+    use crate::ExecutionTable;
+
+    #[test]
+    fn test_check_execution() {
+        let (compile_table, execution_table) = tables();
+        assert_eq!(check_execution(&compile_table, &execution_table), Ok(()));
+
+        let mut wrong = execution_table.clone();
+        wrong.event[1].sp = 3;
+        assert_eq!(
+            check_execution(&compile_table, &wrong),
+            Err(CheckError::SpMismatch {
+                eid: 1,
+                expected: 2,
+                found: 3
+            })
+        );
+
+        let mut wrong = execution_table.clone();
+        wrong.event[1].eid = 3;
+        assert_eq!(
+            check_execution(&compile_table, &wrong),
+            Err(CheckError::EidNotIncreasing {
+                previous: 1,
+                eid: 3
+            })
+        );
+
+        let mut wrong = execution_table.clone();
+        wrong.event[0].inst.iid = 5;
+        assert_eq!(
+            check_execution(&compile_table, &wrong),
+            Err(CheckError::MissingInstruction { eid: 1 })
+        );
+
+        let with_entries = |entries: Vec<MemoryTableEntry>| ExecutionTable {
+            memory: MTable::new(entries),
+            ..execution_table.clone()
+        };
+
+        // `local.get` reads the location it should push to
+        let mut entries = execution_table.memory.entries().clone();
+        entries[2].atype = AccessType::Read;
+        assert_eq!(
+            check_execution(&compile_table, &with_entries(entries)),
+            Err(CheckError::StackReadBeforeWrite { eid: 1, emid: 2 })
+        );
+
+        let mut entries = execution_table.memory.entries().clone();
+        entries[3].value = 7;
+        assert_eq!(
+            check_execution(&compile_table, &with_entries(entries)),
+            Err(CheckError::ReadMismatch { eid: 2, emid: 1 })
+        );
+
+        let mut entries = execution_table.memory.entries().clone();
+        entries.push(MemoryTableEntry {
+            emid: 3,
+            ..entries[3].clone()
+        });
+        assert_eq!(
+            check_execution(&compile_table, &with_entries(entries)),
+            Err(CheckError::MopsMismatch {
+                eid: 2,
+                expected: 2,
+                found: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_check_return() {
+        let (compile_table, execution_table) = tables();
+
+        // the return drops the argument under the result, leaving sp 1
+        let ret = &execution_table.event[1];
+        assert_eq!(ret.sp as i64 + ret.inst.opcode.sp_diff(), 1);
+
+        let mut wrong = execution_table.clone();
+        wrong.event.pop();
+        assert_eq!(
+            check_execution(&compile_table, &wrong),
+            Err(CheckError::MissingReturn { eid: Some(1) })
+        );
+        assert_eq!(
+            check_execution(&compile_table, &ExecutionTable::default()),
+            Err(CheckError::MissingReturn { eid: None })
+        );
+
+        // a return keeping its value over nothing leaves the argument under it
+        let unbalanced = Opcode::Return {
+            drop: 0,
+            keep: vec![ValueType::I64],
+        };
+        let mut compile_table = compile_table;
+        compile_table.instructions[1].opcode = unbalanced.clone();
+        let mut wrong = execution_table;
+        wrong.event[1].inst.opcode = unbalanced;
+        assert_eq!(
+            check_execution(&compile_table, &wrong),
+            Err(CheckError::UnbalancedReturn {
+                eid: 2,
+                expected: 1,
+                found: 2
+            })
+        );
+    }
+}
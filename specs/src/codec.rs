@@ -0,0 +1,62 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Human readable encoding of the tables, for debugging.
+pub fn to_json<T: Serialize>(value: &T) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(value)
+}
+
+pub fn from_json<T: DeserializeOwned>(json: &str) -> serde_json::Result<T> {
+    serde_json::from_str(json)
+}
+
+/// Compact encoding of the tables, to hand a trace over to a prover.
+pub fn to_bytes<T: Serialize>(value: &T) -> bincode::Result<Vec<u8>> {
+    bincode::serialize(value)
+}
+
+pub fn from_bytes<T: DeserializeOwned>(bytes: &[u8]) -> bincode::Result<T> {
+    bincode::deserialize(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codec::{from_bytes, from_json, to_bytes, to_json};
+    use crate::fixture::tables;
+    use crate::types::Value;
+    use crate::{CompileTable, ExecutionTable};
+
+    #[test]
+    fn test_json_round_trip() {
+        let (compile_table, execution_table) = tables();
+
+        let json = to_json(&compile_table).unwrap();
+        assert_eq!(
+            to_json(&from_json::<CompileTable>(&json).unwrap()).unwrap(),
+            json
+        );
+
+        let json = to_json(&execution_table).unwrap();
+        let decoded: ExecutionTable = from_json(&json).unwrap();
+        assert_eq!(to_json(&decoded).unwrap(), json);
+        assert_eq!(decoded.memory.entries(), execution_table.memory.entries());
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let (compile_table, execution_table) = tables();
+
+        let bytes = to_bytes(&compile_table).unwrap();
+        assert_eq!(
+            to_bytes(&from_bytes::<CompileTable>(&bytes).unwrap()).unwrap(),
+            bytes
+        );
+
+        let bytes = to_bytes(&execution_table).unwrap();
+        let decoded: ExecutionTable = from_bytes(&bytes).unwrap();
+        assert_eq!(to_bytes(&decoded).unwrap(), bytes);
+        assert_eq!(decoded.return_values(), vec![Value::I64(-1)]);
+
+        assert!(from_bytes::<ExecutionTable>(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{itable::InstructionTableEntry, step::StepInfo};
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EventTableEntry {
     pub eid: u64,
     pub sp: u64,
@@ -0,0 +1,102 @@
+use crate::etable::EventTableEntry;
+use crate::imtable::InitMemoryTableEntry;
+use crate::itable::{InstructionTableEntry, Opcode};
+use crate::jtable::JumpTableEntry;
+use crate::mtable::{AccessType, LocationType, MTable, MemoryTableEntry, VarType};
+use crate::step::StepInfo;
+use crate::types::ValueType;
+use crate::{CompileTable, ExecutionTable};
+
+/// Tables of `(func (param i64) (result i64) (local.get 0) (return))` run with
+/// `u64::MAX`, consistent with the rules of `check_execution`.
+///
+/// The memory entries are in trace order: the argument, the read and the write
+/// of `local.get`, then the read and the write of `return`.
+pub(crate) fn tables() -> (CompileTable, ExecutionTable) {
+    let inst = |iid, opcode| InstructionTableEntry {
+        moid: 0,
+        mmid: 0,
+        fid: 1,
+        bid: 0,
+        iid,
+        opcode,
+    };
+    let get = inst(
+        0,
+        Opcode::LocalGet {
+            vtype: VarType::I64,
+            offset: 1,
+        },
+    );
+    let ret = inst(
+        1,
+        Opcode::Return {
+            drop: 1,
+            keep: vec![ValueType::I64],
+        },
+    );
+
+    let compile_table = CompileTable {
+        instructions: vec![get.clone(), ret.clone()],
+        init_memory: vec![InitMemoryTableEntry {
+            mmid: 0,
+            offset: 0,
+            value: 7,
+        }],
+        fid_of_entry: 1,
+        fid_of_start: None,
+        module_digest: [3; 32],
+    };
+
+    let stack = |eid, emid, offset, atype| MemoryTableEntry {
+        eid,
+        emid,
+        mmid: 0,
+        offset,
+        ltype: LocationType::Stack,
+        atype,
+        vtype: VarType::I64,
+        value: u64::MAX,
+    };
+    let execution_table = ExecutionTable {
+        event: vec![
+            EventTableEntry {
+                eid: 1,
+                sp: 1,
+                last_jump_eid: 0,
+                inst: get.clone(),
+                step_info: StepInfo::GetLocal {
+                    vtype: VarType::I64,
+                    depth: 1,
+                    value: u64::MAX,
+                },
+            },
+            EventTableEntry {
+                eid: 2,
+                sp: 2,
+                last_jump_eid: 0,
+                inst: ret,
+                step_info: StepInfo::Return {
+                    drop: 1,
+                    keep: vec![ValueType::I64],
+                    drop_values: vec![u64::MAX],
+                    keep_values: vec![u64::MAX],
+                },
+            },
+        ],
+        memory: MTable::new(vec![
+            stack(0, 1, 0, AccessType::Init),
+            stack(1, 1, 0, AccessType::Read),
+            stack(1, 2, 1, AccessType::Write),
+            stack(2, 1, 1, AccessType::Read),
+            stack(2, 2, 0, AccessType::Write),
+        ]),
+        jump: vec![JumpTableEntry {
+            eid: 1,
+            last_jump_eid: 0,
+            inst: Box::new(get),
+        }],
+    };
+
+    (compile_table, execution_table)
+}
@@ -1,3 +1,5 @@
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InitMemoryTableEntry {
     pub mmid: u64,
     pub offset: u64,
@@ -1,11 +1,11 @@
-use crate::itable::OpcodeClass::{Const, Return};
-use num_bigint::BigUint;
 use std::collections::HashSet;
+use strum_macros::EnumIter;
 
 use crate::mtable::VarType;
 use crate::types::ValueType;
 
-#[derive(Clone, Copy, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[derive(Clone, Copy, Debug, Ord, PartialOrd, Eq, PartialEq, Hash, EnumIter)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum OpcodeClass {
     LocalGet = 1,
     Const,
@@ -14,17 +14,19 @@ pub enum OpcodeClass {
 }
 
 impl OpcodeClass {
-    pub fn mops(&self) -> u64 {
+    /// Cost of executing the opcode, a return also tears down the frame.
+    pub fn fuel(&self) -> u64 {
         match self {
             OpcodeClass::LocalGet => 1,
             OpcodeClass::Const => 1,
-            OpcodeClass::Drop => 0,
-            OpcodeClass::Return => 0,
+            OpcodeClass::Drop => 1,
+            OpcodeClass::Return => 2,
         }
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Opcode {
     LocalGet { vtype: VarType, offset: u64 },
     Const { vtype: VarType, value: u64 },
@@ -33,9 +35,31 @@ pub enum Opcode {
 }
 
 impl Opcode {
+    /// Memory operations of the opcode, see `memory_event_of_step` of the runtime.
     pub fn mops(&self) -> u64 {
+        match self {
+            // reads the local and pushes it
+            Opcode::LocalGet { .. } => 2,
+            Opcode::Const { .. } => 1,
+            Opcode::Drop => 0,
+            // reads the kept values and writes them over the dropped ones
+            Opcode::Return { keep, .. } => 2 * keep.len() as u64,
+        }
+    }
+
+    /// Change of the stack pointer, a return moves the kept values down over
+    /// the dropped ones.
+    pub fn sp_diff(&self) -> i64 {
+        match self {
+            Opcode::LocalGet { .. } | Opcode::Const { .. } => 1,
+            Opcode::Drop => -1,
+            Opcode::Return { drop, .. } => -(*drop as i64),
+        }
+    }
+
+    pub fn fuel(&self) -> u64 {
         let opcode_class: OpcodeClass = self.clone().into();
-        opcode_class.mops()
+        opcode_class.fuel()
     }
 
     pub fn vtype(&self) -> Option<VarType> {
@@ -46,49 +70,41 @@ impl Opcode {
     }
 }
 
-pub const OPCODE_CLASS_SHIFT: usize = 96;
-pub const OPCODE_ARG0_SHIFT: usize = 80;
-pub const OPCODE_ARG1_SHIFT: usize = 64;
+/// Columns of an opcode in the instruction table: its class followed by
+/// `OPCODE_COLUMNS - 1` arguments, unused arguments are zero.
+pub const OPCODE_COLUMNS: usize = 4;
 
-impl Into<BigUint> for Opcode {
-    fn into(self) -> BigUint {
-        let bn = match self {
-            Opcode::LocalGet { vtype, offset } => {
-                (BigUint::from(OpcodeClass::LocalGet as u64) << OPCODE_CLASS_SHIFT)
-                    + (BigUint::from(vtype as u64) << OPCODE_ARG0_SHIFT)
-                    + offset
-            }
-            Opcode::Const { vtype, value } => {
-                (BigUint::from(OpcodeClass::Const as u64) << OPCODE_CLASS_SHIFT)
-                    + (BigUint::from(vtype as u64) << OPCODE_ARG0_SHIFT)
-                    + value
-            }
-            Opcode::Drop => BigUint::from(OpcodeClass::Drop as u64) << OPCODE_CLASS_SHIFT,
-            Opcode::Return { drop, keep } => {
-                (BigUint::from(OpcodeClass::Return as u64) << OPCODE_CLASS_SHIFT)
-                    + (BigUint::from(drop as u64) << OPCODE_ARG0_SHIFT)
-                    + (BigUint::from(keep.len() as u64) << OPCODE_ARG0_SHIFT)
-                    + keep.first().map_or(0u64, |x| *x as u64)
-            }
+impl From<Opcode> for [u64; OPCODE_COLUMNS] {
+    fn from(opcode: Opcode) -> [u64; OPCODE_COLUMNS] {
+        let class: OpcodeClass = opcode.clone().into();
+        let args = match opcode {
+            Opcode::LocalGet { vtype, offset } => [vtype as u64, offset, 0],
+            Opcode::Const { vtype, value } => [vtype as u64, value, 0],
+            Opcode::Drop => [0, 0, 0],
+            Opcode::Return { drop, keep } => [
+                drop as u64,
+                keep.len() as u64,
+                keep.first().map_or(0u64, |x| *x as u64),
+            ],
         };
 
-        assert!(bn < BigUint::from(1u64) << 128usize);
-        bn
+        [class as u64, args[0], args[1], args[2]]
     }
 }
 
-impl Into<OpcodeClass> for Opcode {
-    fn into(self) -> OpcodeClass {
-        match self {
+impl From<Opcode> for OpcodeClass {
+    fn from(opcode: Opcode) -> OpcodeClass {
+        match opcode {
             Opcode::LocalGet { .. } => OpcodeClass::LocalGet,
             Opcode::Const { .. } => OpcodeClass::Const,
-            Opcode::Drop { .. } => OpcodeClass::Drop,
+            Opcode::Drop => OpcodeClass::Drop,
             Opcode::Return { .. } => OpcodeClass::Return,
         }
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InstructionTableEntry {
     pub moid: u16,
     pub mmid: u16,
@@ -98,7 +114,7 @@ pub struct InstructionTableEntry {
     pub opcode: Opcode,
 }
 
-pub fn collect_opcodeclass(ientries: &Vec<InstructionTableEntry>) -> HashSet<OpcodeClass> {
+pub fn collect_opcodeclass(ientries: &[InstructionTableEntry]) -> HashSet<OpcodeClass> {
     let mut opcodeclass = HashSet::new();
     ientries.iter().for_each(|entry| {
         opcodeclass.insert(entry.opcode.clone().into());
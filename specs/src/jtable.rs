@@ -1,6 +1,7 @@
 use crate::itable::InstructionTableEntry;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct JumpTableEntry {
     // caller eid (unique)
     pub eid: u64,
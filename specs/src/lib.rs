@@ -1,25 +1,87 @@
 use crate::{
-    etable::EventTableEntry, imtable::InitMemoryTableEntry, itable::InstructionTableEntry,
-    jtable::JumpTableEntry, mtable::MTable,
+    etable::EventTableEntry,
+    imtable::InitMemoryTableEntry,
+    itable::InstructionTableEntry,
+    jtable::JumpTableEntry,
+    mtable::{AccessType, LocationType, MTable},
+    step::StepInfo,
+    types::Value,
 };
 
+pub mod checker;
+#[cfg(feature = "serde")]
+pub mod codec;
 pub mod etable;
+#[cfg(test)]
+mod fixture;
 pub mod imtable;
 pub mod itable;
 pub mod jtable;
 pub mod mtable;
+pub mod segment;
 pub mod step;
 pub mod types;
 
-#[derive(Default)]
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CompileTable {
     pub instructions: Vec<InstructionTableEntry>,
     pub init_memory: Vec<InitMemoryTableEntry>,
+    pub fid_of_entry: u16,
+    /// The start function of the module, it runs before the entry.
+    pub fid_of_start: Option<u16>,
+    /// Blake2b-256 digest of the module bytes the tables are compiled from.
+    pub module_digest: [u8; 32],
 }
 
-#[derive(Default)]
+impl CompileTable {
+    /// Function of the first event, the start function when there is one.
+    pub fn first_fid(&self) -> u16 {
+        self.fid_of_start.unwrap_or(self.fid_of_entry)
+    }
+}
+
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ExecutionTable {
     pub event: Vec<EventTableEntry>,
     pub memory: MTable,
     pub jump: Vec<JumpTableEntry>,
 }
+
+impl ExecutionTable {
+    /// Arguments of the invoked function, taken from the stack init entries.
+    pub fn arguments(&self) -> Vec<u64> {
+        let mut arguments = self
+            .memory
+            .entries()
+            .iter()
+            .filter(|entry| entry.ltype == LocationType::Stack && entry.atype == AccessType::Init)
+            .collect::<Vec<_>>();
+        arguments.sort_by_key(|entry| entry.offset);
+
+        arguments.into_iter().map(|entry| entry.value).collect()
+    }
+
+    /// Fuel consumed by the events, see `OpcodeClass::fuel`.
+    pub fn fuel(&self) -> u64 {
+        self.event
+            .iter()
+            .map(|entry| entry.inst.opcode.fuel())
+            .sum()
+    }
+
+    /// Values kept by the last `Return`, i.e. the results of the invoked function.
+    pub fn return_values(&self) -> Vec<Value> {
+        match self.event.last().map(|entry| &entry.step_info) {
+            Some(StepInfo::Return {
+                keep, keep_values, ..
+            }) => keep
+                .iter()
+                .zip(keep_values)
+                .map(|(vtype, value)| Value::from_raw(*vtype, *value))
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
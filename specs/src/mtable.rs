@@ -1,14 +1,16 @@
-use crate::types::Value;
+use crate::types::{UnsupportedValueType, Value};
 use parity_wasm::elements::ValueType;
 use strum_macros::EnumIter;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LocationType {
     Heap = 0,
     Stack = 1,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AccessType {
     Read = 1,
     Write = 2,
@@ -16,6 +18,7 @@ pub enum AccessType {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, EnumIter)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum VarType {
     U8 = 1,
     I8,
@@ -27,14 +30,11 @@ pub enum VarType {
     I64,
 }
 
-impl From<ValueType> for VarType {
-    fn from(value: ValueType) -> VarType {
-        match value {
-            ValueType::I32 => Self::I32,
-            ValueType::I64 => Self::I64,
-            ValueType::F32 => todo!(),
-            ValueType::F64 => todo!(),
-        }
+impl TryFrom<ValueType> for VarType {
+    type Error = UnsupportedValueType;
+
+    fn try_from(value: ValueType) -> Result<VarType, Self::Error> {
+        crate::types::ValueType::try_from(value).map(VarType::from)
     }
 }
 
@@ -43,11 +43,18 @@ impl From<crate::types::ValueType> for VarType {
         match value {
             crate::types::ValueType::I32 => VarType::I32,
             crate::types::ValueType::I64 => VarType::I64,
-            _ => todo!(),
+            crate::types::ValueType::U32 => VarType::U32,
+            crate::types::ValueType::U64 => VarType::U64,
         }
     }
 }
 
+impl From<&Value> for VarType {
+    fn from(value: &Value) -> VarType {
+        value.vtype().into()
+    }
+}
+
 impl VarType {
     pub fn byte_size(&self) -> u64 {
         match self {
@@ -63,7 +70,8 @@ impl VarType {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MemoryTableEntry {
     pub eid: u64,
     pub emid: u64,
@@ -81,7 +89,8 @@ impl MemoryTableEntry {
     }
 }
 
-#[derive(Default)]
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MTable(Vec<MemoryTableEntry>);
 
 impl MTable {
@@ -91,7 +100,7 @@ impl MTable {
 
     pub fn sort(&mut self) {
         self.0
-            .sort_by_key(|item| (item.ltype, item.mmid, item.eid, item.emid))
+            .sort_by_key(|item| (item.ltype, item.mmid, item.offset, item.eid, item.emid))
     }
 
     pub fn entries(&self) -> &Vec<MemoryTableEntry> {
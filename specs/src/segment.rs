@@ -0,0 +1,220 @@
+use crate::{
+    etable::EventTableEntry,
+    mtable::{AccessType, LocationType, MTable, MemoryTableEntry, VarType},
+    types::Value,
+    ExecutionTable,
+};
+
+/// Registers of the machine right before an event is executed.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MachineState {
+    pub eid: u64,
+    pub sp: u64,
+    pub fid: u16,
+    pub iid: u16,
+}
+
+impl From<&EventTableEntry> for MachineState {
+    fn from(event: &EventTableEntry) -> Self {
+        MachineState {
+            eid: event.eid,
+            sp: event.sp,
+            fid: event.inst.fid,
+            iid: event.inst.iid,
+        }
+    }
+}
+
+/// Type an argument takes on the stack: wasm has no unsigned types, so that
+/// of the parameter it is passed to.
+pub fn argument_vtype(arg: &Value) -> VarType {
+    match arg {
+        Value::I32(_) | Value::U32(_) => VarType::I32,
+        Value::I64(_) | Value::U64(_) => VarType::I64,
+    }
+}
+
+/// Registers and stack before the first event of an execution starting in
+/// `first_fid` with `arguments`, where the first segment of a proof must start.
+pub fn initial_state(first_fid: u16, arguments: &[Value]) -> (MachineState, Vec<MemoryTableEntry>) {
+    let state = MachineState {
+        eid: 1,
+        sp: arguments.len() as u64,
+        fid: first_fid,
+        iid: 0,
+    };
+    let stack = arguments
+        .iter()
+        .enumerate()
+        .map(|(i, arg)| MemoryTableEntry {
+            eid: 0,
+            emid: i as u64 + 1,
+            mmid: 0,
+            offset: i as u64,
+            ltype: LocationType::Stack,
+            atype: AccessType::Init,
+            vtype: argument_vtype(arg),
+            value: arg.raw(),
+        })
+        .collect();
+
+    (state, stack)
+}
+
+/// Public part of a segment, what its proof commits to.
+///
+/// The stack at a boundary holds the last value of every stack location touched
+/// before it, as `Init` entries. Heap is not part of the boundary since no
+/// instruction writes to it yet.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SegmentBoundary {
+    pub start: MachineState,
+    /// `None` for the last segment, no event follows it.
+    pub end: Option<MachineState>,
+    pub start_stack: Vec<MemoryTableEntry>,
+    pub end_stack: Vec<MemoryTableEntry>,
+    /// Values kept by the `Return` ending the last segment, empty for the others.
+    pub returns: Vec<Value>,
+    /// Fuel consumed by the events of the segment.
+    pub fuel: u64,
+}
+
+impl SegmentBoundary {
+    /// Whether `self` continues exactly where `prev` stopped.
+    pub fn follows(&self, prev: &SegmentBoundary) -> bool {
+        prev.end == Some(self.start) && prev.end_stack == self.start_stack
+    }
+
+    /// Registers after the last event: those the next segment starts with, or
+    /// after the last segment a halted machine whose stack holds the returns.
+    pub fn end_state(&self) -> MachineState {
+        self.end.unwrap_or(MachineState {
+            sp: self.returns.len() as u64,
+            ..Default::default()
+        })
+    }
+}
+
+/// A slice of an execution that is proven on its own.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Segment {
+    pub boundary: SegmentBoundary,
+    pub tables: ExecutionTable,
+}
+
+/// Stack snapshot after all memory entries of events before `eid`.
+fn stack_before(mtable: &MTable, eid: Option<u64>) -> Vec<MemoryTableEntry> {
+    let mut stack: Vec<MemoryTableEntry> = vec![];
+
+    // entries are sorted by location then by eid, the last one of a location wins
+    for entry in mtable.entries() {
+        if entry.ltype != LocationType::Stack
+            || (entry.atype != AccessType::Init && eid.is_some_and(|eid| entry.eid >= eid))
+        {
+            continue;
+        }
+
+        match stack.last_mut() {
+            Some(last) if last.is_same_location(entry) => {
+                last.vtype = entry.vtype;
+                last.value = entry.value;
+            }
+            _ => stack.push(entry.clone()),
+        }
+    }
+
+    stack
+        .into_iter()
+        .enumerate()
+        .map(|(i, entry)| MemoryTableEntry {
+            eid: 0,
+            emid: i as u64 + 1,
+            atype: AccessType::Init,
+            ..entry
+        })
+        .collect()
+}
+
+impl ExecutionTable {
+    /// Splits the execution into segments of at most `events_per_segment` events.
+    pub fn segments(&self, events_per_segment: usize) -> Vec<Segment> {
+        assert!(events_per_segment > 0);
+
+        if self.event.is_empty() {
+            return vec![Segment {
+                boundary: SegmentBoundary {
+                    start: MachineState::default(),
+                    end: None,
+                    start_stack: stack_before(&self.memory, None),
+                    end_stack: stack_before(&self.memory, None),
+                    returns: vec![],
+                    fuel: 0,
+                },
+                tables: self.clone(),
+            }];
+        }
+
+        let chunks = self.event.chunks(events_per_segment).collect::<Vec<_>>();
+
+        chunks
+            .iter()
+            .enumerate()
+            .map(|(i, events)| {
+                let start_eid = events[0].eid;
+                let end_eid = chunks.get(i + 1).map(|next| next[0].eid);
+                let in_segment = |eid: u64| eid >= start_eid && end_eid.is_none_or(|end| eid < end);
+
+                let start_stack = stack_before(&self.memory, Some(start_eid));
+                let mut memory = MTable::new(
+                    start_stack
+                        .iter()
+                        .cloned()
+                        .chain(
+                            self.memory
+                                .entries()
+                                .iter()
+                                .filter(|entry| {
+                                    entry.atype != AccessType::Init && in_segment(entry.eid)
+                                })
+                                .cloned(),
+                        )
+                        .collect(),
+                );
+                memory.sort();
+
+                let tables = ExecutionTable {
+                    event: events.to_vec(),
+                    memory,
+                    jump: self
+                        .jump
+                        .iter()
+                        .filter(|entry| in_segment(entry.eid))
+                        .cloned()
+                        .collect(),
+                };
+
+                let end = chunks.get(i + 1).map(|next| (&next[0]).into());
+                let returns = if end.is_none() {
+                    tables.return_values()
+                } else {
+                    vec![]
+                };
+
+                Segment {
+                    boundary: SegmentBoundary {
+                        start: (&events[0]).into(),
+                        end,
+                        start_stack,
+                        end_stack: stack_before(&self.memory, end_eid),
+                        returns,
+                        fuel: tables.fuel(),
+                    },
+                    tables,
+                }
+            })
+            .collect()
+    }
+}
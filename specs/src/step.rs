@@ -2,6 +2,7 @@ use crate::mtable::VarType;
 use crate::types::ValueType;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StepInfo {
     BrIfNez {
         value: i32,
@@ -1,4 +1,7 @@
-#[derive(Clone, Copy, Debug)]
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ValueType {
     I32,
     I64,
@@ -6,18 +9,34 @@ pub enum ValueType {
     U64,
 }
 
-impl From<parity_wasm::elements::ValueType> for ValueType {
-    fn from(value: parity_wasm::elements::ValueType) -> Self {
+/// A wasm type without circuit, i.e. a floating point type.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UnsupportedValueType(pub parity_wasm::elements::ValueType);
+
+impl fmt::Display for UnsupportedValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unsupported type {}", self.0)
+    }
+}
+
+impl std::error::Error for UnsupportedValueType {}
+
+impl TryFrom<parity_wasm::elements::ValueType> for ValueType {
+    type Error = UnsupportedValueType;
+
+    fn try_from(value: parity_wasm::elements::ValueType) -> Result<Self, Self::Error> {
         match value {
-            parity_wasm::elements::ValueType::I32 => ValueType::I32,
-            parity_wasm::elements::ValueType::I64 => ValueType::I64,
-            parity_wasm::elements::ValueType::F32 => todo!(),
-            parity_wasm::elements::ValueType::F64 => todo!(),
+            parity_wasm::elements::ValueType::I32 => Ok(ValueType::I32),
+            parity_wasm::elements::ValueType::I64 => Ok(ValueType::I64),
+            parity_wasm::elements::ValueType::F32 | parity_wasm::elements::ValueType::F64 => {
+                Err(UnsupportedValueType(value))
+            }
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Value {
     I32(i32),
     I64(i64),
@@ -25,8 +44,165 @@ pub enum Value {
     U64(u64),
 }
 
-#[derive(Debug)]
-pub enum CompileError {}
+impl Value {
+    pub fn vtype(&self) -> ValueType {
+        match self {
+            Value::I32(_) => ValueType::I32,
+            Value::I64(_) => ValueType::I64,
+            Value::U32(_) => ValueType::U32,
+            Value::U64(_) => ValueType::U64,
+        }
+    }
+
+    /// Value of type `vtype` whose bits are `raw`, see `raw`.
+    pub fn from_raw(vtype: ValueType, raw: u64) -> Value {
+        match vtype {
+            ValueType::I32 => Value::I32(raw as u32 as i32),
+            ValueType::I64 => Value::I64(raw as i64),
+            ValueType::U32 => Value::U32(raw as u32),
+            ValueType::U64 => Value::U64(raw),
+        }
+    }
+
+    /// Bits of the value zero extended to 64 bits, as stored in the memory table.
+    pub fn raw(&self) -> u64 {
+        match self {
+            Value::I32(v) => *v as u32 as u64,
+            Value::I64(v) => *v as u64,
+            Value::U32(v) => *v as u64,
+            Value::U64(v) => *v,
+        }
+    }
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueType::I32 => write!(f, "i32"),
+            ValueType::I64 => write!(f, "i64"),
+            ValueType::U32 => write!(f, "u32"),
+            ValueType::U64 => write!(f, "u64"),
+        }
+    }
+}
+
+/// Formats as `<type>:<value>`, e.g. `i32:-1`, as the command line takes values.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::I32(v) => write!(f, "{}:{}", self.vtype(), v),
+            Value::I64(v) => write!(f, "{}:{}", self.vtype(), v),
+            Value::U32(v) => write!(f, "{}:{}", self.vtype(), v),
+            Value::U64(v) => write!(f, "{}:{}", self.vtype(), v),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CompileError {
+    /// Malformed text format, `position` is the 1-based line and column when known.
+    Parse {
+        message: String,
+        position: Option<(u32, u32)>,
+    },
+    Validation(String),
+    /// An instruction without a circuit, at `iid` in the body of function `fid`.
+    UnsupportedInstruction {
+        fid: u16,
+        iid: u16,
+        instruction: String,
+    },
+    /// A parameter, result or local of function `fid` with a type without circuit.
+    UnsupportedType {
+        fid: u16,
+        vtype: String,
+    },
+    MissingExport(String),
+    Instantiation(String),
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::Parse {
+                message,
+                position: Some((line, column)),
+            } => write!(f, "parse error at {}:{}: {}", line, column, message),
+            CompileError::Parse {
+                message,
+                position: None,
+            } => write!(f, "parse error: {}", message),
+            CompileError::Validation(message) => write!(f, "invalid module: {}", message),
+            CompileError::UnsupportedInstruction {
+                fid,
+                iid,
+                instruction,
+            } => write!(
+                f,
+                "unsupported instruction {} at fid {} iid {}",
+                instruction, fid, iid
+            ),
+            CompileError::UnsupportedType { fid, vtype } => {
+                write!(f, "unsupported type {} in fid {}", vtype, fid)
+            }
+            CompileError::MissingExport(name) => write!(f, "no function exported as {}", name),
+            CompileError::Instantiation(message) => {
+                write!(f, "failed to instantiate module: {}", message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExecutionError {
+    Instantiation(String),
+    /// The entry could not be invoked, e.g. with arguments not matching its signature.
+    Invocation(String),
+    /// The execution trapped, `kind` describes the trap.
+    Trap {
+        kind: String,
+    },
+    /// A host function failed.
+    Host(String),
+    /// The execution takes more than `limit` steps.
+    StepLimitExceeded {
+        limit: usize,
+    },
+    /// The execution consumes more than the fuel `budget`.
+    OutOfFuel {
+        budget: u64,
+    },
+    /// The arguments do not match the parameters of the entry.
+    ArgumentMismatch {
+        expected: Vec<ValueType>,
+        found: Vec<ValueType>,
+    },
+}
+
+impl fmt::Display for ExecutionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecutionError::Instantiation(message) => {
+                write!(f, "failed to instantiate module: {}", message)
+            }
+            ExecutionError::Invocation(message) => write!(f, "failed to invoke entry: {}", message),
+            ExecutionError::Trap { kind } => write!(f, "execution trapped: {}", kind),
+            ExecutionError::Host(message) => write!(f, "host error: {}", message),
+            ExecutionError::StepLimitExceeded { limit } => {
+                write!(f, "execution exceeds the limit of {} steps", limit)
+            }
+            ExecutionError::OutOfFuel { budget } => {
+                write!(f, "execution exceeds the fuel budget of {}", budget)
+            }
+            ExecutionError::ArgumentMismatch { expected, found } => write!(
+                f,
+                "entry expects arguments {:?}, found {:?}",
+                expected, found
+            ),
+        }
+    }
+}
 
-#[derive(Debug)]
-pub enum ExecutionError {}
+impl std::error::Error for ExecutionError {}
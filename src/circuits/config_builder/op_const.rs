@@ -3,9 +3,8 @@ use halo2_proofs::{
     arithmetic::FieldExt,
     plonk::{Advice, Column, ConstraintSystem, Expression, VirtualCells},
 };
-use num_bigint::BigUint;
 use specs::etable::EventTableEntry;
-use specs::itable::{OpcodeClass, OPCODE_ARG0_SHIFT, OPCODE_CLASS_SHIFT};
+use specs::itable::{OpcodeClass, OPCODE_COLUMNS};
 use specs::mtable::VarType;
 use specs::step::StepInfo;
 use std::marker::PhantomData;
@@ -13,12 +12,13 @@ use std::marker::PhantomData;
 use crate::circuits::event::EventCommonConfig;
 use crate::circuits::event::{EventOpcodeConfig, EventOpcodeConfigBuilder};
 use crate::circuits::instruction::InstructionConfig;
+use crate::circuits::io::IOConfig;
 use crate::circuits::jump::JumpConfig;
 use crate::circuits::memory::MemoryConfig;
 use crate::circuits::range::RangeConfig;
 use crate::circuits::utils::tvalue::TValueConfig;
-use crate::circuits::utils::{bn_to_field, Context};
-use crate::{constant, constant_from, cur};
+use crate::circuits::utils::Context;
+use crate::{constant_from, cur};
 
 pub struct ConstConfig<F: FieldExt> {
     tvalue: TValueConfig<F>,
@@ -37,9 +37,11 @@ impl<F: FieldExt> EventOpcodeConfigBuilder<F> for ConstConfigBuilder {
         instruction_table: &InstructionConfig<F>,
         memory_table: &MemoryConfig<F>,
         jump_table: &JumpConfig<F>,
+        io_table: &IOConfig<F>,
     ) -> Box<dyn EventOpcodeConfig<F>> {
-        let tvalue =
-            TValueConfig::configure(meta, cols, range_table, |meta| cur!(meta, opcode_bit));
+        let tvalue = TValueConfig::configure(meta, cols, range_table, |meta| {
+            common.sel(meta) * cur!(meta, opcode_bit)
+        });
 
         memory_table.configure_stack_write_in_table(
             "const mlookup",
@@ -60,15 +62,13 @@ impl<F: FieldExt> EventOpcodeConfigBuilder<F> for ConstConfigBuilder {
 }
 
 impl<F: FieldExt> EventOpcodeConfig<F> for ConstConfig<F> {
-    fn opcode(&self, meta: &mut VirtualCells<'_, F>) -> Expression<F> {
-        // [(2 + vartype) << (64+13) + value] * enable
-        // 2(10) << 77
-        (constant!(bn_to_field(&(BigUint::from(OpcodeClass::Const as u64) << OPCODE_CLASS_SHIFT)))
-            // vartype * (1 << 77)
-            + cur!(meta, self.tvalue.vtype) * constant!(bn_to_field(&(BigUint::from(1u64) << OPCODE_ARG0_SHIFT)))
-            // value
-            + cur!(meta, self.tvalue.value.value))
-            * cur!(meta, self.enable)
+    fn opcode(&self, meta: &mut VirtualCells<'_, F>) -> [Expression<F>; OPCODE_COLUMNS] {
+        [
+            constant_from!(OpcodeClass::Const as u64),
+            cur!(meta, self.tvalue.vtype),
+            cur!(meta, self.tvalue.value.value),
+            constant_from!(0u64),
+        ]
     }
 
     fn sp_diff(&self, meta: &mut VirtualCells<'_, F>) -> Expression<F> {
@@ -77,6 +77,10 @@ impl<F: FieldExt> EventOpcodeConfig<F> for ConstConfig<F> {
         constant_from!(1u64) * cur!(meta, self.enable)
     }
 
+    fn mops(&self, meta: &mut VirtualCells<'_, F>) -> Expression<F> {
+        constant_from!(1u64) * cur!(meta, self.enable)
+    }
+
     fn assign(&self, ctx: &mut Context<'_, F>, entry: &EventTableEntry) -> Result<(), Error> {
         match entry.step_info {
             StepInfo::I32Const { value } => {
@@ -112,8 +116,8 @@ mod tests {
                 "#;
 
         let compiler = WasmInterpreter::new();
-        let compiled_module = compiler.compile(textual_repr).unwrap();
-        let execution_log = compiler.run(&compiled_module, "test", vec![]).unwrap();
+        let compiled_module = compiler.compile(textual_repr, "test").unwrap();
+        let execution_log = compiler.run(&compiled_module, vec![]).unwrap();
 
         run_test_circuit::<Fp>(compiled_module.tables, execution_log.tables).unwrap()
     }
@@ -1,17 +1,17 @@
 use halo2_proofs::arithmetic::FieldExt;
 use halo2_proofs::plonk::{Advice, Column, ConstraintSystem, Error, Expression, VirtualCells};
-use num_bigint::BigUint;
 use specs::etable::EventTableEntry;
-use specs::itable::OpcodeClass;
+use specs::itable::{OpcodeClass, OPCODE_COLUMNS};
 use std::marker::PhantomData;
 
 use crate::circuits::event::{EventCommonConfig, EventOpcodeConfig, EventOpcodeConfigBuilder};
 use crate::circuits::instruction::InstructionConfig;
+use crate::circuits::io::IOConfig;
 use crate::circuits::jump::JumpConfig;
 use crate::circuits::memory::MemoryConfig;
 use crate::circuits::range::RangeConfig;
-use crate::circuits::utils::{bn_to_field, Context};
-use crate::{constant, cur};
+use crate::circuits::utils::Context;
+use crate::{constant, constant_from, cur};
 
 pub struct DropConfig<F: FieldExt> {
     enable: Column<Advice>,
@@ -30,6 +30,7 @@ impl<F: FieldExt> EventOpcodeConfigBuilder<F> for DropConfigBuilder {
         itable: &InstructionConfig<F>,
         mtable: &MemoryConfig<F>,
         jtable: &JumpConfig<F>,
+        iotable: &IOConfig<F>,
     ) -> Box<dyn EventOpcodeConfig<F>> {
         Box::new(DropConfig {
             enable: opcode_bit,
@@ -39,11 +40,13 @@ impl<F: FieldExt> EventOpcodeConfigBuilder<F> for DropConfigBuilder {
 }
 
 impl<F: FieldExt> EventOpcodeConfig<F> for DropConfig<F> {
-    fn opcode(&self, meta: &mut VirtualCells<'_, F>) -> Expression<F> {
-        // (3 << 77) * enable.cur
-        constant!(bn_to_field(
-            &(BigUint::from(OpcodeClass::Drop as u64) << (64 + 13))
-        )) * cur!(meta, self.enable)
+    fn opcode(&self, meta: &mut VirtualCells<'_, F>) -> [Expression<F>; OPCODE_COLUMNS] {
+        [
+            constant_from!(OpcodeClass::Drop as u64),
+            constant_from!(0u64),
+            constant_from!(0u64),
+            constant_from!(0u64),
+        ]
     }
 
     fn sp_diff(&self, meta: &mut VirtualCells<'_, F>) -> Expression<F> {
@@ -51,6 +54,10 @@ impl<F: FieldExt> EventOpcodeConfig<F> for DropConfig<F> {
         constant!(-F::one()) * cur!(meta, self.enable)
     }
 
+    fn mops(&self, _meta: &mut VirtualCells<'_, F>) -> Expression<F> {
+        constant_from!(0u64)
+    }
+
     fn assign(&self, ctx: &mut Context<'_, F>, entry: &EventTableEntry) -> Result<(), Error> {
         Ok(())
     }
@@ -3,10 +3,11 @@ use std::marker::PhantomData;
 use crate::circuits::event::EventCommonConfig;
 use crate::circuits::event::{EventOpcodeConfig, EventOpcodeConfigBuilder};
 use crate::circuits::instruction::InstructionConfig;
+use crate::circuits::io::IOConfig;
 use crate::circuits::jump::JumpConfig;
 use crate::circuits::memory::MemoryConfig;
-use crate::circuits::utils::{bn_to_field, Context};
-use crate::{constant, constant_from, cur};
+use crate::circuits::utils::Context;
+use crate::{constant_from, cur};
 
 use crate::circuits::range::RangeConfig;
 use crate::circuits::utils::tvalue::TValueConfig;
@@ -15,14 +16,13 @@ use halo2_proofs::{
     arithmetic::FieldExt,
     plonk::{Advice, Column, ConstraintSystem, Expression, VirtualCells},
 };
-use num_bigint::BigUint;
 use specs::etable::EventTableEntry;
 use specs::itable::OpcodeClass;
-use specs::itable::OPCODE_ARG0_SHIFT;
-use specs::itable::OPCODE_CLASS_SHIFT;
+use specs::itable::OPCODE_COLUMNS;
 use specs::step::StepInfo;
 
 pub struct LocalGetConfig<F: FieldExt> {
+    enable: Column<Advice>,
     offset: Column<Advice>,
     tvalue: TValueConfig<F>,
     _mark: PhantomData<F>,
@@ -40,10 +40,12 @@ impl<F: FieldExt> EventOpcodeConfigBuilder<F> for LocalGetConfigBuilder {
         instruction_table: &InstructionConfig<F>,
         memory_table: &MemoryConfig<F>,
         jump_table: &JumpConfig<F>,
+        io_table: &IOConfig<F>,
     ) -> Box<dyn EventOpcodeConfig<F>> {
         let offset = cols.next().unwrap();
-        let tvalue =
-            TValueConfig::configure(meta, cols, range_table, |meta| cur!(meta, opcode_bit));
+        let tvalue = TValueConfig::configure(meta, cols, range_table, |meta| {
+            common.sel(meta) * cur!(meta, opcode_bit)
+        });
 
         range_table.configure_in_common_range(meta, "localget offset range", |meta| {
             cur!(meta, opcode_bit) * cur!(meta, offset)
@@ -55,7 +57,7 @@ impl<F: FieldExt> EventOpcodeConfigBuilder<F> for LocalGetConfigBuilder {
             |meta| cur!(meta, opcode_bit),
             |meta| cur!(meta, common.eid),
             |meta| constant_from!(1u64),
-            |meta| cur!(meta, common.sp),
+            |meta| cur!(meta, common.sp) - cur!(meta, offset),
             |meta| cur!(meta, tvalue.vtype),
             |meta| cur!(meta, tvalue.value.value),
         );
@@ -72,6 +74,7 @@ impl<F: FieldExt> EventOpcodeConfigBuilder<F> for LocalGetConfigBuilder {
         );
 
         Box::new(LocalGetConfig {
+            enable: opcode_bit,
             offset,
             tvalue,
             _mark: PhantomData,
@@ -80,17 +83,21 @@ impl<F: FieldExt> EventOpcodeConfigBuilder<F> for LocalGetConfigBuilder {
 }
 
 impl<F: FieldExt> EventOpcodeConfig<F> for LocalGetConfig<F> {
-    fn opcode(&self, meta: &mut VirtualCells<'_, F>) -> Expression<F> {
-        // (1 << 64) + offset
-        constant!(bn_to_field(
-            &(BigUint::from(OpcodeClass::LocalGet as u64) << OPCODE_CLASS_SHIFT)
-        )) + cur!(meta, self.tvalue.vtype)
-            * constant!(bn_to_field(&(BigUint::from(1u64) << OPCODE_ARG0_SHIFT)))
-            + cur!(meta, self.offset)
+    fn opcode(&self, meta: &mut VirtualCells<'_, F>) -> [Expression<F>; OPCODE_COLUMNS] {
+        [
+            constant_from!(OpcodeClass::LocalGet as u64),
+            cur!(meta, self.tvalue.vtype),
+            cur!(meta, self.offset),
+            constant_from!(0u64),
+        ]
     }
 
     fn sp_diff(&self, meta: &mut VirtualCells<'_, F>) -> Expression<F> {
-        constant_from!(1u64)
+        constant_from!(1u64) * cur!(meta, self.enable)
+    }
+
+    fn mops(&self, meta: &mut VirtualCells<'_, F>) -> Expression<F> {
+        constant_from!(2u64) * cur!(meta, self.enable)
     }
 
     fn assign(&self, ctx: &mut Context<'_, F>, entry: &EventTableEntry) -> Result<(), Error> {
@@ -119,3 +126,32 @@ impl<F: FieldExt> EventOpcodeConfig<F> for LocalGetConfig<F> {
         OpcodeClass::LocalGet
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::runtime::{WasmInterpreter, WasmRuntime};
+    use halo2_proofs::pairing::bn256::Fr as Fp;
+    use specs::types::Value;
+
+    use crate::test::test_circuit_builder::run_test_circuit;
+
+    #[test]
+    fn test_argument_ok() {
+        let textual_repr = r#"
+                (module
+                    (func (export "test") (param i32)
+                      (local.get 0)
+                      (drop)
+                    )
+                   )
+                "#;
+
+        let compiler = WasmInterpreter::new();
+        let compiled_module = compiler.compile(textual_repr, "test").unwrap();
+        let execution_log = compiler.run(&compiled_module, vec![Value::I32(3)]).unwrap();
+
+        assert_eq!(execution_log.tables.arguments(), vec![3]);
+
+        run_test_circuit::<Fp>(compiled_module.tables, execution_log.tables).unwrap()
+    }
+}
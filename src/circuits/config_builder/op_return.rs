@@ -1,22 +1,20 @@
 use crate::circuits::event::{EventCommonConfig, EventOpcodeConfig, EventOpcodeConfigBuilder};
 use crate::circuits::instruction::InstructionConfig;
+use crate::circuits::io::{IOConfig, IOType};
 use crate::circuits::jump::JumpConfig;
 use crate::circuits::memory::MemoryConfig;
 use crate::circuits::range::RangeConfig;
-use crate::circuits::utils::bn_to_field;
 use crate::circuits::utils::tvalue::TValueConfig;
 use crate::circuits::utils::Context;
-use crate::{constant, constant_from, cur};
+use crate::{constant, constant_from, cur, next};
 use halo2_proofs::arithmetic::FieldExt;
 use halo2_proofs::plonk::{Advice, Column, ConstraintSystem, Error, Expression, VirtualCells};
-use num_bigint::BigUint;
 use specs::etable::EventTableEntry;
-use specs::itable::OPCODE_ARG0_SHIFT;
-use specs::itable::OPCODE_CLASS_SHIFT;
-use specs::itable::{OpcodeClass, OPCODE_ARG1_SHIFT};
+use specs::itable::{OpcodeClass, OPCODE_COLUMNS};
 use specs::mtable::VarType;
 
 pub struct ReturnConfig<F: FieldExt> {
+    enable: Column<Advice>,
     drop: Column<Advice>,
     keep: Column<Advice>,
     tvalue: TValueConfig<F>,
@@ -34,13 +32,23 @@ impl<F: FieldExt> EventOpcodeConfigBuilder<F> for ReturnConfigBuilder {
         itable: &InstructionConfig<F>,
         mtable: &MemoryConfig<F>,
         jtable: &JumpConfig<F>,
+        iotable: &IOConfig<F>,
     ) -> Box<dyn EventOpcodeConfig<F>> {
         let drop = cols.next().unwrap();
         let keep = cols.next().unwrap();
-        let tvalue = TValueConfig::configure(meta, cols, rtable, |meta| cur!(meta, opcode_bit));
+        // a return without result leaves the typed value empty
+        let tvalue = TValueConfig::configure(meta, cols, rtable, |meta| {
+            common.sel(meta) * cur!(meta, opcode_bit) * cur!(meta, keep)
+        });
 
+        // the columns are shared with the other opcodes
         meta.create_gate("keep is bit", |meta| {
-            vec![cur!(meta, keep) * (cur!(meta, keep) - constant_from!(1))]
+            vec![
+                common.sel(meta)
+                    * cur!(meta, opcode_bit)
+                    * cur!(meta, keep)
+                    * (cur!(meta, keep) - constant_from!(1)),
+            ]
         });
 
         rtable.configure_in_common_range(meta, "return drop range", |meta| {
@@ -53,7 +61,7 @@ impl<F: FieldExt> EventOpcodeConfigBuilder<F> for ReturnConfigBuilder {
             |meta| cur!(meta, opcode_bit) * cur!(meta, keep),
             |meta| cur!(meta, common.eid),
             |_meta| constant_from!(1u64),
-            |meta| cur!(meta, common.sp),
+            |meta| cur!(meta, common.sp) - constant_from!(1u64),
             |meta| cur!(meta, tvalue.vtype),
             |meta| cur!(meta, tvalue.value.value),
         );
@@ -64,28 +72,64 @@ impl<F: FieldExt> EventOpcodeConfigBuilder<F> for ReturnConfigBuilder {
             |meta| cur!(meta, opcode_bit) * cur!(meta, keep),
             |meta| cur!(meta, common.eid),
             |meta| constant_from!(2u64),
-            |meta| cur!(meta, common.sp) - cur!(meta, drop),
+            |meta| cur!(meta, common.sp) - constant_from!(1u64) - cur!(meta, drop),
             |meta| cur!(meta, tvalue.vtype),
             |meta| cur!(meta, tvalue.value.value),
         );
 
-        Box::new(ReturnConfig { drop, keep, tvalue })
+        // The last event of the last segment returns from the invoked export,
+        // its kept value is public.
+        iotable.configure_in_table(meta, "return value in instance", |meta| {
+            cur!(meta, opcode_bit)
+                * cur!(meta, keep)
+                * (constant_from!(1u64) - next!(meta, common.enable))
+                * (constant_from!(1u64) - cur!(meta, common.has_end))
+                * iotable.encode(
+                    constant_from!(IOType::Return),
+                    constant_from!(0u64),
+                    cur!(meta, tvalue.vtype),
+                    cur!(meta, tvalue.value.value),
+                )
+        });
+
+        // The stack then only holds the kept values, whose number is public as
+        // the sp of the end registers, so that no return is left out.
+        meta.create_gate("return keeps the results", |meta| {
+            vec![
+                common.sel(meta)
+                    * cur!(meta, opcode_bit)
+                    * (constant_from!(1u64) - next!(meta, common.enable))
+                    * (constant_from!(1u64) - cur!(meta, common.has_end))
+                    * (cur!(meta, keep) - next!(meta, common.sp)),
+            ]
+        });
+
+        Box::new(ReturnConfig {
+            enable: opcode_bit,
+            drop,
+            keep,
+            tvalue,
+        })
     }
 }
 
 impl<F: FieldExt> EventOpcodeConfig<F> for ReturnConfig<F> {
-    fn opcode(&self, meta: &mut VirtualCells<'_, F>) -> Expression<F> {
-        constant!(bn_to_field(
-            &(BigUint::from(OpcodeClass::Return as u64) << OPCODE_CLASS_SHIFT)
-        )) + cur!(meta, self.drop)
-            * constant!(bn_to_field(&(BigUint::from(1u64) << OPCODE_ARG0_SHIFT)))
-            + cur!(meta, self.keep)
-                * constant!(bn_to_field(&(BigUint::from(1u64) << OPCODE_ARG1_SHIFT)))
-            + cur!(meta, self.tvalue.vtype)
+    fn opcode(&self, meta: &mut VirtualCells<'_, F>) -> [Expression<F>; OPCODE_COLUMNS] {
+        [
+            constant_from!(OpcodeClass::Return as u64),
+            cur!(meta, self.drop),
+            cur!(meta, self.keep),
+            cur!(meta, self.tvalue.vtype),
+        ]
     }
 
     fn sp_diff(&self, meta: &mut VirtualCells<'_, F>) -> Expression<F> {
-        cur!(meta, self.keep) - cur!(meta, self.drop)
+        // the kept values move down over the dropped ones
+        constant!(-F::one()) * cur!(meta, self.enable) * cur!(meta, self.drop)
+    }
+
+    fn mops(&self, meta: &mut VirtualCells<'_, F>) -> Expression<F> {
+        constant_from!(2u64) * cur!(meta, self.enable) * cur!(meta, self.keep)
     }
 
     fn assign(&self, ctx: &mut Context<'_, F>, entry: &EventTableEntry) -> Result<(), Error> {
@@ -125,3 +169,87 @@ impl<F: FieldExt> EventOpcodeConfig<F> for ReturnConfig<F> {
         OpcodeClass::Return
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::pairing::bn256::Fr as Fp;
+    use specs::mtable::{MemoryTableEntry, VarType};
+    use specs::types::Value;
+
+    use crate::circuits::event::CircuitOptions;
+    use crate::runtime::{WasmInterpreter, WasmRuntime};
+    use crate::test::test_circuit_builder::{run_test_circuit, run_test_segment};
+
+    const TEXTUAL_REPR: &str = r#"
+            (module
+                (func (export "test") (param i32) (result i32)
+                  (local.get 0)
+                )
+               )
+            "#;
+
+    #[test]
+    fn test_return_value_ok() {
+        let compiler = WasmInterpreter::new();
+        let compiled_module = compiler.compile(TEXTUAL_REPR, "test").unwrap();
+        let execution_log = compiler.run(&compiled_module, vec![Value::I32(3)]).unwrap();
+
+        assert_eq!(execution_log.tables.return_values(), vec![Value::I32(3)]);
+
+        run_test_circuit::<Fp>(compiled_module.tables, execution_log.tables).unwrap()
+    }
+
+    #[test]
+    fn test_wrong_return_value() {
+        let compiler = WasmInterpreter::new();
+        let compiled_module = compiler.compile(TEXTUAL_REPR, "test").unwrap();
+        let execution_log = compiler.run(&compiled_module, vec![Value::I32(3)]).unwrap();
+
+        let options = CircuitOptions::of(&compiled_module.tables);
+        let mut segment = execution_log.tables.segments(usize::MAX).pop().unwrap();
+        segment.boundary.returns = vec![Value::I32(4)];
+
+        assert!(run_test_segment::<Fp>(compiled_module.tables, segment, options).is_err());
+    }
+
+    #[test]
+    fn test_missing_return_value() {
+        let compiler = WasmInterpreter::new();
+        let compiled_module = compiler.compile(TEXTUAL_REPR, "test").unwrap();
+        let execution_log = compiler.run(&compiled_module, vec![Value::I32(3)]).unwrap();
+
+        let options = CircuitOptions::of(&compiled_module.tables);
+        let mut segment = execution_log.tables.segments(usize::MAX).pop().unwrap();
+        segment.boundary.returns = vec![];
+
+        assert!(run_test_segment::<Fp>(compiled_module.tables, segment, options).is_err());
+    }
+
+    #[test]
+    fn test_wrong_argument() {
+        let compiler = WasmInterpreter::new();
+        let compiled_module = compiler.compile(TEXTUAL_REPR, "test").unwrap();
+        let execution_log = compiler.run(&compiled_module, vec![Value::I32(3)]).unwrap();
+
+        let options = CircuitOptions::of(&compiled_module.tables);
+        let segment = execution_log.tables.segments(usize::MAX).pop().unwrap();
+
+        // an argument the execution did not read, or one of another type
+        for start_stack in [
+            vec![segment.boundary.start_stack[0].clone(); 2],
+            vec![MemoryTableEntry {
+                vtype: VarType::I64,
+                ..segment.boundary.start_stack[0].clone()
+            }],
+        ] {
+            let mut segment = segment.clone();
+            segment.boundary.start_stack = start_stack;
+            assert!(run_test_segment::<Fp>(
+                compiled_module.tables.clone(),
+                segment,
+                options.clone()
+            )
+            .is_err());
+        }
+    }
+}
@@ -1,22 +1,63 @@
 use halo2_proofs::arithmetic::FieldExt;
-use halo2_proofs::circuit::Cell;
-use halo2_proofs::plonk::{Advice, Column, ConstraintSystem, Error, Expression, VirtualCells};
+use halo2_proofs::circuit::AssignedCell;
+use halo2_proofs::plonk::{
+    Advice, Column, ConstraintSystem, Error, Expression, Fixed, VirtualCells,
+};
+use halo2_proofs::poly::Rotation;
+use rayon::prelude::*;
 use specs::etable::EventTableEntry;
-use specs::itable::{Opcode, OpcodeClass};
-use std::collections::BTreeMap;
+use specs::itable::{collect_opcodeclass, OpcodeClass, OPCODE_COLUMNS};
+use specs::segment::MachineState;
+use specs::CompileTable;
+use std::collections::{BTreeMap, BTreeSet};
 use std::marker::PhantomData;
 use std::rc::Rc;
+use strum::IntoEnumIterator;
 
 use crate::circuits::config_builder::op_const::ConstConfigBuilder;
 use crate::circuits::config_builder::op_drop::DropConfigBuilder;
 use crate::circuits::config_builder::op_local_get::LocalGetConfigBuilder;
 use crate::circuits::config_builder::op_return::ReturnConfigBuilder;
-use crate::circuits::instruction::{encode_inst_expr, InstructionConfig};
+use crate::circuits::instruction::{InstructionConfig, INSTRUCTION_COLUMNS};
+use crate::circuits::io::{IOConfig, IOType};
 use crate::circuits::jump::JumpConfig;
 use crate::circuits::memory::MemoryConfig;
 use crate::circuits::range::RangeConfig;
-use crate::circuits::utils::{bn_to_field, Context};
-use crate::{constant, constant_from, cur, next, pre};
+use crate::circuits::state::{StateConfig, StateType};
+use crate::circuits::utils::allocator::ColumnAllocator;
+use crate::circuits::utils::Context;
+use crate::{constant_from, cur, next, pre};
+
+/// Circuits built by `EventConfig::configure`, keys are only valid for the
+/// options they were generated with.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CircuitOptions {
+    /// Opcode classes which get a circuit.
+    pub opcode_classes: BTreeSet<OpcodeClass>,
+    /// Makes the fuel consumed by the events a public output.
+    pub fuel_metering: bool,
+}
+
+impl Default for CircuitOptions {
+    fn default() -> Self {
+        CircuitOptions {
+            opcode_classes: OpcodeClass::iter().collect(),
+            fuel_metering: false,
+        }
+    }
+}
+
+impl CircuitOptions {
+    /// Only the opcodes used by `compile_table` get a circuit.
+    pub fn of(compile_table: &CompileTable) -> CircuitOptions {
+        CircuitOptions {
+            opcode_classes: collect_opcodeclass(&compile_table.instructions)
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
 
 pub trait EventOpcodeConfigBuilder<F: FieldExt> {
     fn configure(
@@ -28,18 +69,24 @@ pub trait EventOpcodeConfigBuilder<F: FieldExt> {
         itable: &InstructionConfig<F>,
         mtable: &MemoryConfig<F>,
         jtable: &JumpConfig<F>,
+        iotable: &IOConfig<F>,
     ) -> Box<dyn EventOpcodeConfig<F>>;
 }
 
 pub trait EventOpcodeConfig<F: FieldExt> {
-    fn opcode(&self, meta: &mut VirtualCells<'_, F>) -> Expression<F>;
+    /// Class and arguments of the opcode, see `OPCODE_COLUMNS`.
+    fn opcode(&self, meta: &mut VirtualCells<'_, F>) -> [Expression<F>; OPCODE_COLUMNS];
     fn sp_diff(&self, meta: &mut VirtualCells<'_, F>) -> Expression<F>;
+    /// Memory operations of the row, zero unless the opcode bit is set, see `Opcode::mops`.
+    fn mops(&self, meta: &mut VirtualCells<'_, F>) -> Expression<F>;
     fn assign(&self, ctx: &mut Context<'_, F>, entry: &EventTableEntry) -> Result<(), Error>;
     fn opcode_class(&self) -> OpcodeClass;
 }
 
 #[derive(Clone)]
 pub struct EventCommonConfig {
+    /// See `StateConfig::sel`.
+    pub sel: Column<Fixed>,
     pub enable: Column<Advice>,
     pub rest_mops: Column<Advice>,
     pub eid: Column<Advice>,
@@ -49,7 +96,15 @@ pub struct EventCommonConfig {
     pub iid: Column<Advice>,
     pub mmid: Column<Advice>,
     pub sp: Column<Advice>,
-    pub opcode: Column<Advice>,
+    pub opcode: [Column<Advice>; OPCODE_COLUMNS],
+    /// One on every row when a segment follows, see `MemoryConfig::has_end`.
+    pub has_end: Column<Advice>,
+}
+
+impl EventCommonConfig {
+    pub fn sel<F: FieldExt>(&self, meta: &mut VirtualCells<'_, F>) -> Expression<F> {
+        meta.query_fixed(self.sel, Rotation::cur())
+    }
 }
 
 #[derive(Clone)]
@@ -57,29 +112,41 @@ pub struct EventConfig<F: FieldExt> {
     common_config: EventCommonConfig,
     opcode_bitmaps: BTreeMap<OpcodeClass, Column<Advice>>,
     opcode_configs: BTreeMap<OpcodeClass, Rc<Box<dyn EventOpcodeConfig<F>>>>,
+    /// Fuel of the remaining events, only with `CircuitOptions::fuel_metering`.
+    rest_fuel: Option<Column<Advice>>,
     _mark: PhantomData<F>,
 }
 
 impl<F: FieldExt> EventConfig<F> {
     pub fn configure(
         meta: &mut ConstraintSystem<F>,
-        cols: &mut (impl Iterator<Item = Column<Advice>> + Clone),
+        cols: &mut ColumnAllocator,
         range_table: &RangeConfig<F>,
         inst_config: &InstructionConfig<F>,
         memory_table: &MemoryConfig<F>,
         jump_table: &JumpConfig<F>,
+        io_table: &IOConfig<F>,
+        state_table: &StateConfig<F>,
+        options: &CircuitOptions,
     ) -> EventConfig<F> {
-        let enable = cols.next().unwrap();
-        let eid = cols.next().unwrap();
-        let moid = cols.next().unwrap();
-        let fid = cols.next().unwrap();
-        let bid = cols.next().unwrap();
-        let iid = cols.next().unwrap();
-        let mmid = cols.next().unwrap();
-        let sp = cols.next().unwrap();
-        let opcode = cols.next().unwrap();
-        let rest_mops = cols.next().unwrap();
+        let mut common_cols = cols.table("event");
+        let enable = common_cols.next().unwrap();
+        let eid = common_cols.next().unwrap();
+        let moid = common_cols.next().unwrap();
+        let fid = common_cols.next().unwrap();
+        let bid = common_cols.next().unwrap();
+        let iid = common_cols.next().unwrap();
+        let mmid = common_cols.next().unwrap();
+        let sp = common_cols.next().unwrap();
+        let opcode = [(); OPCODE_COLUMNS].map(|_| common_cols.next().unwrap());
+        let rest_mops = common_cols.next().unwrap();
+        meta.enable_equality(rest_mops);
+        let has_end = common_cols.next().unwrap();
+        meta.enable_equality(has_end);
+        let rest_fuel = options.fuel_metering.then(|| common_cols.next().unwrap());
+
         let common_config = EventCommonConfig {
+            sel: state_table.sel_column(),
             enable,
             eid,
             moid,
@@ -90,6 +157,7 @@ impl<F: FieldExt> EventConfig<F> {
             sp,
             opcode,
             rest_mops,
+            has_end,
         };
 
         let mut opcode_bitmaps_vec = vec![];
@@ -97,44 +165,64 @@ impl<F: FieldExt> EventConfig<F> {
         let mut opcode_configs: BTreeMap<OpcodeClass, Rc<Box<dyn EventOpcodeConfig<F>>>> =
             BTreeMap::new();
 
+        let opcode_classes = &options.opcode_classes;
+
         macro_rules! configure [
-            ($($x:ident),*) => ({
-                $($x{}; opcode_bitmaps_vec.push(cols.next().unwrap());)*
+            ($($x:ident => $class:expr),*) => ({
+                $(
+                    if opcode_classes.contains(&$class) {
+                        opcode_bitmaps_vec.push(cols.table("event").next().unwrap());
+                    }
+                )*
 
+                // a row enables a single opcode, so their columns are shared
+                let mut shared = cols.share();
                 let mut opcode_bitmaps_iter = opcode_bitmaps_vec.iter();
                 $(
-                    let opcode_bit = opcode_bitmaps_iter.next().unwrap();
-                    let config = $x::configure(
-                        meta,
-                        &common_config,
-                        opcode_bit.clone(),
-                        &mut cols.clone(),
-                        range_table,
-                        inst_config,
-                        memory_table,
-                        jump_table,
-                    );
-                    opcode_bitmaps.insert(config.opcode_class(), opcode_bit.clone());
-                    opcode_configs.insert(config.opcode_class(), Rc::new(config));
+                    if opcode_classes.contains(&$class) {
+                        let opcode_bit = opcode_bitmaps_iter.next().unwrap();
+                        let config = $x::configure(
+                            meta,
+                            &common_config,
+                            opcode_bit.clone(),
+                            &mut cols.shared(&mut shared, stringify!($class)),
+                            range_table,
+                            inst_config,
+                            memory_table,
+                            jump_table,
+                            io_table,
+                        );
+                        opcode_bitmaps.insert(config.opcode_class(), opcode_bit.clone());
+                        opcode_configs.insert(config.opcode_class(), Rc::new(config));
+                    }
                 )*
+                cols.end_share(shared);
             })
         ];
 
         configure![
-            ConstConfigBuilder,
-            DropConfigBuilder,
-            LocalGetConfigBuilder,
-            ReturnConfigBuilder
+            ConstConfigBuilder => OpcodeClass::Const,
+            DropConfigBuilder => OpcodeClass::Drop,
+            LocalGetConfigBuilder => OpcodeClass::LocalGet,
+            ReturnConfigBuilder => OpcodeClass::Return
         ];
 
         meta.create_gate("opcode consistent", |meta| {
-            let mut acc = constant_from!(0u64);
-            for (_, config) in opcode_configs.iter() {
-                acc = acc + config.opcode(meta);
+            let sel = common_config.sel(meta);
+            let mut acc = [(); OPCODE_COLUMNS].map(|_| constant_from!(0u64));
+            for (opcode_class, config) in opcode_configs.iter() {
+                let bit = cur!(meta, opcode_bitmaps[opcode_class]);
+                for (acc, expr) in acc.iter_mut().zip(config.opcode(meta)) {
+                    *acc = acc.clone() + bit.clone() * expr;
+                }
             }
 
-            // advice.opcode - acc == 0
-            vec![cur!(meta, opcode) - acc]
+            // advice.opcode - acc == 0 for every column
+            opcode
+                .iter()
+                .zip(acc)
+                .map(|(col, acc)| sel.clone() * (cur!(meta, *col) - acc))
+                .collect::<Vec<_>>()
         });
 
         meta.create_gate("sp diff consistent", |meta| {
@@ -143,34 +231,46 @@ impl<F: FieldExt> EventConfig<F> {
                 acc = acc + config.sp_diff(meta);
             }
 
-            // sp + sum(diff) - sp.next == 0
-            vec![cur!(meta, sp) + acc - next!(meta, sp)]
+            // sp + sum(diff) - sp.next == 0, the row after the last event holds
+            // the registers it ends with
+            vec![
+                common_config.sel(meta)
+                    * cur!(meta, enable)
+                    * (cur!(meta, sp) + acc - next!(meta, sp)),
+            ]
         });
 
         for (_, bit) in opcode_bitmaps.iter() {
             meta.create_gate("opcode_bitmaps asssert bit", |meta| {
                 // bit * (bit - 1)
                 // bit == 0 || bit == 1
-                vec![cur!(meta, *bit) * (cur!(meta, *bit) - constant_from!(1u64))]
+                vec![
+                    common_config.sel(meta)
+                        * cur!(meta, *bit)
+                        * (cur!(meta, *bit) - constant_from!(1u64)),
+                ]
             });
         }
 
         meta.create_gate("opcode_bitmaps pick one", |meta| {
-            // sum(bits) - 1 == 0
+            // sum(bits) - enable == 0, padding rows pick none and without any
+            // opcode class no row is enabled
             vec![
-                opcode_bitmaps
-                    .iter()
-                    .map(|(_, x)| cur!(meta, *x))
-                    .reduce(|acc, x| acc + x)
-                    .unwrap()
-                    - constant_from!(1u64),
+                common_config.sel(meta)
+                    * (opcode_bitmaps
+                        .iter()
+                        .map(|(_, x)| cur!(meta, *x))
+                        .fold(constant_from!(0u64), |acc, x| acc + x)
+                        - cur!(meta, enable)),
             ]
         });
 
         meta.create_gate("eid increase", |meta| {
-            // eid.cur - eid.pre - 1 == 0
+            // eid.cur - eid.pre - 1 == 0, but on the first row
             vec![
-                cur!(meta, common_config.enable)
+                common_config.sel(meta)
+                    * (constant_from!(1) - state_table.first(meta))
+                    * cur!(meta, common_config.enable)
                     * (cur!(meta, common_config.eid)
                         - pre!(meta, common_config.eid)
                         - constant_from!(1u64)),
@@ -178,26 +278,28 @@ impl<F: FieldExt> EventConfig<F> {
         });
 
         inst_config.configure_in_table(meta, "instruction in table", |meta| {
-            cur!(meta, enable)
-                * encode_inst_expr(
-                    cur!(meta, common_config.moid),
-                    cur!(meta, common_config.mmid),
-                    cur!(meta, common_config.fid),
-                    cur!(meta, common_config.bid),
-                    cur!(meta, common_config.iid),
-                    cur!(meta, common_config.opcode),
-                )
+            let enable = cur!(meta, enable);
+            let addr = [
+                common_config.moid,
+                common_config.mmid,
+                common_config.fid,
+                common_config.bid,
+                common_config.iid,
+            ];
+
+            let mut columns = addr.into_iter().chain(common_config.opcode);
+            [(); INSTRUCTION_COLUMNS].map(|_| enable.clone() * cur!(meta, columns.next().unwrap()))
         });
 
         meta.create_gate("rest_mops decrease", |meta| {
-            let curr_mops = opcode_bitmaps
-                .iter()
-                .map(|(opcode_class, x)| cur!(meta, *x) * constant_from!(opcode_class.mops()))
-                .reduce(|acc, x| acc + x)
-                .unwrap();
+            let curr_mops = opcode_configs
+                .values()
+                .map(|config| config.mops(meta))
+                .fold(constant_from!(0u64), |acc, x| acc + x);
 
             vec![
-                cur!(meta, common_config.enable)
+                common_config.sel(meta)
+                    * cur!(meta, common_config.enable)
                     * (cur!(meta, common_config.rest_mops)
                         - next!(meta, common_config.rest_mops)
                         - curr_mops),
@@ -206,14 +308,100 @@ impl<F: FieldExt> EventConfig<F> {
 
         meta.create_gate("rest_mops is zero at end", |meta| {
             vec![
-                (cur!(meta, common_config.enable) - constant_from!(1))
+                common_config.sel(meta)
+                    * (cur!(meta, common_config.enable) - constant_from!(1))
                     * cur!(meta, common_config.rest_mops),
             ]
         });
 
+        if let Some(rest_fuel) = rest_fuel {
+            meta.create_gate("rest_fuel decrease", |meta| {
+                let curr_fuel = opcode_bitmaps
+                    .iter()
+                    .map(|(opcode_class, x)| cur!(meta, *x) * constant_from!(opcode_class.fuel()))
+                    .fold(constant_from!(0u64), |acc, x| acc + x);
+
+                let sel = common_config.sel(meta);
+                vec![
+                    sel.clone()
+                        * cur!(meta, common_config.enable)
+                        * (cur!(meta, rest_fuel) - next!(meta, rest_fuel) - curr_fuel),
+                    sel * (cur!(meta, common_config.enable) - constant_from!(1))
+                        * cur!(meta, rest_fuel),
+                ]
+            });
+
+            // the fuel of the first row is consumed by the whole segment
+            io_table.configure_in_table(meta, "event fuel in instance", |meta| {
+                state_table.first(meta)
+                    * io_table.encode(
+                        constant_from!(IOType::Fuel),
+                        constant_from!(0u64),
+                        constant_from!(0u64),
+                        cur!(meta, rest_fuel),
+                    )
+            });
+        }
+
+        state_table.configure_in_table(meta, "event start state in instance", |meta| {
+            state_table.first(meta)
+                * state_table.encode_registers(
+                    StateType::Start,
+                    cur!(meta, eid),
+                    cur!(meta, sp),
+                    cur!(meta, fid),
+                    cur!(meta, iid),
+                )
+        });
+
+        // the row after the last event holds the registers of the next segment
+        state_table.configure_in_table(meta, "event end state in instance", |meta| {
+            cur!(meta, enable)
+                * (constant_from!(1) - next!(meta, enable))
+                * state_table.encode_registers(
+                    StateType::End,
+                    next!(meta, eid),
+                    next!(meta, sp),
+                    next!(meta, fid),
+                    next!(meta, iid),
+                )
+        });
+
+        meta.create_gate("event has_end is the same on every row", |meta| {
+            vec![
+                common_config.sel(meta)
+                    * next!(meta, common_config.enable)
+                    * (next!(meta, common_config.has_end) - cur!(meta, common_config.has_end)),
+            ]
+        });
+
+        // Nothing follows the last segment, it must run until the invoked export
+        // returns: it has events and the last one is a `Return`, the only way out
+        // of a function without calls.
+        meta.create_gate("event last segment returns", |meta| {
+            let sel = common_config.sel(meta);
+            let enable = cur!(meta, common_config.enable);
+            let is_last = constant_from!(1) - cur!(meta, common_config.has_end);
+            let return_bit = opcode_bitmaps
+                .get(&OpcodeClass::Return)
+                .map_or(constant_from!(0), |bit| cur!(meta, *bit));
+
+            vec![
+                sel.clone()
+                    * state_table.first(meta)
+                    * is_last.clone()
+                    * (constant_from!(1) - enable.clone()),
+                sel * enable
+                    * (constant_from!(1) - next!(meta, common_config.enable))
+                    * is_last
+                    * (constant_from!(1) - return_bit),
+            ]
+        });
+
         meta.create_gate("enable is bit", |meta| {
             vec![
-                (cur!(meta, common_config.enable) - constant_from!(1))
+                common_config.sel(meta)
+                    * (cur!(meta, common_config.enable) - constant_from!(1))
                     * cur!(meta, common_config.enable),
             ]
         });
@@ -222,11 +410,17 @@ impl<F: FieldExt> EventConfig<F> {
             common_config,
             opcode_bitmaps,
             opcode_configs,
+            rest_fuel,
             _mark: PhantomData,
         }
     }
 }
 
+pub struct EventTableCells<F: FieldExt> {
+    pub rest_mops: AssignedCell<F, F>,
+    pub has_end: AssignedCell<F, F>,
+}
+
 pub struct EventChip<F: FieldExt> {
     config: EventConfig<F>,
     _phantom: PhantomData<F>,
@@ -244,55 +438,63 @@ impl<F: FieldExt> EventChip<F> {
         &self,
         ctx: &mut Context<'_, F>,
         entries: &Vec<EventTableEntry>,
-    ) -> Result<Cell, Error> {
+        end: Option<MachineState>,
+        has_end: bool,
+    ) -> Result<EventTableCells<F>, Error> {
         let mut rest_mops_cell = None;
+        let mut has_end_cell = None;
         let mut rest_mops = entries
             .iter()
             .fold(0, |acc, entry| acc + entry.inst.opcode.mops());
-
-        for (i, entry) in entries.into_iter().enumerate() {
-            ctx.region.assign_advice(
-                || "event enable",
-                self.config.common_config.enable,
-                ctx.offset,
-                || Ok(F::one()),
-            )?;
-
-            macro_rules! assign {
-                ($x: ident, $value: expr) => {
-                    ctx.region.assign_advice(
-                        || concat!("event ", stringify!($x)),
-                        self.config.common_config.$x,
-                        ctx.offset,
-                        || Ok($value),
-                    )?;
-                };
-            }
-
-            macro_rules! assign_as_u64 {
-                ($x: ident, $value: expr) => {
-                    assign!($x, F::from($value as u64))
-                };
+        let mut rest_fuel = entries
+            .iter()
+            .fold(0, |acc, entry| acc + entry.inst.opcode.fuel());
+
+        // values of the common columns are computed in parallel before assignment
+        let common = &self.config.common_config;
+        let rows = entries
+            .par_iter()
+            .map(|entry| {
+                let opcode: [u64; OPCODE_COLUMNS] = entry.inst.opcode.clone().into();
+
+                [
+                    (common.enable, 1),
+                    (common.eid, entry.eid),
+                    (common.moid, entry.inst.moid as u64),
+                    (common.fid, entry.inst.fid as u64),
+                    (common.bid, entry.inst.bid as u64),
+                    (common.iid, entry.inst.iid as u64),
+                    (common.mmid, entry.inst.mmid as u64),
+                    (common.sp, entry.sp),
+                    (common.has_end, has_end as u64),
+                ]
+                .into_iter()
+                .chain(common.opcode.into_iter().zip(opcode))
+                .map(|(col, value)| (col, F::from(value)))
+                .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        for (i, (entry, row)) in entries.iter().zip(rows).enumerate() {
+            for (col, value) in row {
+                let cell =
+                    ctx.region
+                        .assign_advice(|| "event common", col, ctx.offset, || Ok(value))?;
+
+                // the flag of the first row is bound to the state instance
+                if i == 0 && col == common.has_end {
+                    has_end_cell = Some(cell);
+                }
             }
 
-            assign_as_u64!(enable, 1u64);
-            assign_as_u64!(eid, entry.eid);
-            assign_as_u64!(moid, entry.inst.moid);
-            assign_as_u64!(fid, entry.inst.fid);
-            assign_as_u64!(bid, entry.inst.bid);
-            assign_as_u64!(iid, entry.inst.iid);
-            assign_as_u64!(mmid, entry.inst.mmid);
-            assign_as_u64!(sp, entry.sp);
-            assign!(opcode, bn_to_field(&(entry.inst.opcode.clone().into())));
-
             let opcode_class = entry.inst.opcode.clone().into();
 
             ctx.region.assign_advice(
-                || concat!("event opcode"),
+                || "event opcode",
                 self.config
                     .opcode_bitmaps
                     .get(&opcode_class)
-                    .unwrap()
+                    .ok_or(Error::Synthesis)?
                     .clone(),
                 ctx.offset,
                 || Ok(F::one()),
@@ -301,27 +503,118 @@ impl<F: FieldExt> EventChip<F> {
             self.config
                 .opcode_configs
                 .get(&opcode_class)
-                .unwrap()
+                .ok_or(Error::Synthesis)?
                 .as_ref()
                 .as_ref()
                 .assign(ctx, entry)?;
 
             let cell = ctx.region.assign_advice(
-                || concat!("event rest_mops"),
+                || "event rest_mops",
                 self.config.common_config.rest_mops,
                 ctx.offset,
                 || Ok(rest_mops.into()),
             )?;
 
             if i == 0 {
-                rest_mops_cell = Some(cell.cell());
+                rest_mops_cell = Some(cell);
             }
 
             rest_mops -= entry.inst.opcode.mops();
 
+            if let Some(col) = self.config.rest_fuel {
+                ctx.region.assign_advice(
+                    || "event rest_fuel",
+                    col,
+                    ctx.offset,
+                    || Ok(rest_fuel.into()),
+                )?;
+            }
+            rest_fuel -= entry.inst.opcode.fuel();
+
             ctx.next();
         }
 
-        Ok(rest_mops_cell.unwrap())
+        if let Some(end) = end {
+            for (col, value) in [
+                (self.config.common_config.eid, end.eid),
+                (self.config.common_config.sp, end.sp),
+                (self.config.common_config.fid, end.fid as u64),
+                (self.config.common_config.iid, end.iid as u64),
+            ] {
+                ctx.region.assign_advice(
+                    || "event end state",
+                    col,
+                    ctx.offset,
+                    || Ok(value.into()),
+                )?;
+            }
+        }
+
+        // Without witness (e.g. key generation) the copied cell still exists,
+        // so that the permutation does not depend on the trace.
+        if entries.is_empty() {
+            rest_mops_cell = Some(ctx.region.assign_advice(
+                || "event rest_mops",
+                self.config.common_config.rest_mops,
+                ctx.offset,
+                || Ok(F::zero()),
+            )?);
+            has_end_cell = Some(ctx.region.assign_advice(
+                || "event has_end",
+                self.config.common_config.has_end,
+                ctx.offset,
+                || Ok(F::from(has_end as u64)),
+            )?);
+        }
+
+        Ok(EventTableCells {
+            rest_mops: rest_mops_cell.unwrap(),
+            has_end: has_end_cell.unwrap(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::pairing::bn256::Fr as Fp;
+    use specs::{CompileTable, ExecutionTable};
+
+    use crate::circuits::event::CircuitOptions;
+    use crate::circuits::wasm_circuit::WasmCircuit;
+    use crate::runtime::{WasmInterpreter, WasmRuntime};
+    use crate::test::test_circuit_builder::run_test_segment;
+
+    #[test]
+    fn test_options_without_opcode() {
+        let options = CircuitOptions::of(&CompileTable::default());
+        assert!(options.opcode_classes.is_empty());
+
+        let circuit =
+            WasmCircuit::<Fp>::new(CompileTable::default(), ExecutionTable::default(), options);
+        assert!(circuit.column_usage().check().is_ok());
+    }
+
+    #[test]
+    fn test_fuel_metering() {
+        let textual_repr = r#"
+                (module
+                    (func (export "test")
+                      (i32.const 0)
+                      (drop)
+                    )
+                   )
+                "#;
+
+        let compiler = WasmInterpreter::new();
+        let compiled_module = compiler.compile(textual_repr, "test").unwrap();
+        let execution_log = compiler.run(&compiled_module, vec![]).unwrap();
+        assert_eq!(execution_log.tables.fuel(), 4);
+
+        let options = CircuitOptions {
+            fuel_metering: true,
+            ..CircuitOptions::of(&compiled_module.tables)
+        };
+        let segment = execution_log.tables.segments(usize::MAX).pop().unwrap();
+        run_test_segment::<Fp>(compiled_module.tables, segment, options).unwrap()
     }
 }
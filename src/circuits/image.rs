@@ -0,0 +1,184 @@
+use halo2_proofs::arithmetic::FieldExt;
+use halo2_proofs::circuit::{AssignedCell, Layouter};
+use halo2_proofs::plonk::{Advice, Column, ConstraintSystem, Error, Fixed, Instance, Selector};
+use halo2_proofs::poly::Rotation;
+use num_bigint::BigUint;
+use specs::CompileTable;
+use std::marker::PhantomData;
+
+use crate::circuits::instruction::Encode as _;
+use crate::circuits::utils::poseidon::{PoseidonConfig, PoseidonSpec, RATE, ROUNDS, T};
+use crate::circuits::utils::{bn_to_field, Context};
+use crate::circuits::Encode;
+use crate::cur;
+
+pub const IMAGE_COLUMNS: usize = T + RATE;
+
+/// Hash inputs of the image: the header followed by the encoded instruction
+/// table and init memory table, in table order.
+fn image_inputs<F: FieldExt>(compile_table: &CompileTable) -> Vec<F> {
+    let header = [
+        F::from(compile_table.instructions.len() as u64),
+        F::from(compile_table.init_memory.len() as u64),
+        F::from(compile_table.first_fid() as u64),
+        // two halves so that the digest fits any field
+        bn_to_field(&BigUint::from_bytes_le(&compile_table.module_digest[..16])),
+        bn_to_field(&BigUint::from_bytes_le(&compile_table.module_digest[16..])),
+        F::from(compile_table.fid_of_entry as u64),
+    ];
+
+    header
+        .into_iter()
+        .chain(
+            compile_table
+                .instructions
+                .iter()
+                .flat_map(|entry| entry.encode().map(F::from)),
+        )
+        .chain(
+            compile_table
+                .init_memory
+                .iter()
+                .map(|entry| bn_to_field(&entry.encode())),
+        )
+        .collect()
+}
+
+/// Image id of a compiled program, the poseidon hash over the instruction
+/// table, the init memory table, the start and entry functions and the module digest.
+pub fn image_id<F: FieldExt>(compile_table: &CompileTable) -> F {
+    PoseidonSpec::new().hash(&image_inputs(compile_table))
+}
+
+/// Rows taken by the image region, see `ImageChip::assign`.
+pub fn image_rows<F: FieldExt>(compile_table: &CompileTable) -> usize {
+    let blocks = (image_inputs::<F>(compile_table).len() + RATE - 1) / RATE;
+
+    blocks * (1 + ROUNDS) + 1
+}
+
+#[derive(Clone)]
+pub struct ImageConfig<F: FieldExt> {
+    poseidon: PoseidonConfig<F>,
+    inputs: [Column<Fixed>; RATE],
+    s_inputs: Selector,
+    instance: Column<Instance>,
+    _mark: PhantomData<F>,
+}
+
+impl<F: FieldExt> ImageConfig<F> {
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        cols: &mut impl Iterator<Item = Column<Advice>>,
+    ) -> ImageConfig<F> {
+        let poseidon = PoseidonConfig::configure(meta, cols);
+        let inputs = [(); RATE].map(|_| meta.fixed_column());
+        let s_inputs = meta.selector();
+        let instance = meta.instance_column();
+
+        meta.enable_equality(instance);
+        meta.enable_equality(poseidon.state[1]);
+        meta.enable_equality(poseidon.inputs[2]);
+
+        // the absorbed blocks are fixed by the keys, so the image id commits to the program
+        meta.create_gate("image inputs are fixed", |meta| {
+            let s = meta.query_selector(s_inputs);
+            (0..RATE)
+                .map(|i| {
+                    s.clone()
+                        * (cur!(meta, poseidon.inputs[i])
+                            - meta.query_fixed(inputs[i], Rotation::cur()))
+                })
+                .collect::<Vec<_>>()
+        });
+
+        ImageConfig {
+            poseidon,
+            inputs,
+            s_inputs,
+            instance,
+            _mark: PhantomData,
+        }
+    }
+
+    pub fn instance(&self) -> Column<Instance> {
+        self.instance
+    }
+}
+
+pub struct ImageChip<F: FieldExt> {
+    config: ImageConfig<F>,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: FieldExt> ImageChip<F> {
+    pub fn new(config: ImageConfig<F>) -> ImageChip<F> {
+        ImageChip {
+            config,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the cell of the first fid and the cell of the image id.
+    pub fn assign(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        compile_table: &CompileTable,
+    ) -> Result<(AssignedCell<F, F>, AssignedCell<F, F>), Error> {
+        let inputs = image_inputs::<F>(compile_table);
+
+        layouter.assign_region(
+            || "image",
+            |region| {
+                let mut ctx = Context::new(region);
+
+                // each block takes one absorbing row followed by the round rows
+                for (block, values) in inputs.chunks(RATE).enumerate() {
+                    let offset = ctx.offset + block * (1 + ROUNDS);
+                    self.config.s_inputs.enable(&mut ctx.region, offset)?;
+                    for i in 0..RATE {
+                        let value = values.get(i).cloned().unwrap_or(F::zero());
+                        ctx.region.assign_fixed(
+                            || "image input",
+                            self.config.inputs[i],
+                            offset,
+                            || Ok(value),
+                        )?;
+                    }
+                }
+
+                let (input_cells, id) = self.config.poseidon.assign(&mut ctx, &inputs)?;
+
+                Ok((input_cells[2].clone(), id))
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::pairing::bn256::Fr as Fp;
+
+    use crate::circuits::image::image_id;
+    use crate::runtime::{WasmInterpreter, WasmRuntime};
+
+    #[test]
+    fn test_image_id() {
+        let compile = |textual_repr: &str| {
+            WasmInterpreter::new()
+                .compile(textual_repr, "test")
+                .unwrap()
+                .tables
+        };
+        let image = compile(r#"(module (func (export "test") (i32.const 0) (drop)))"#);
+
+        assert_eq!(image_id::<Fp>(&image), image_id::<Fp>(&image.clone()));
+        // another instruction, another entry
+        for other in [
+            r#"(module (func (export "test") (i32.const 1) (drop)))"#,
+            r#"(module (func) (func (export "test") (i32.const 0) (drop)))"#,
+        ] {
+            assert_ne!(image_id::<Fp>(&image), image_id::<Fp>(&compile(other)));
+        }
+    }
+}
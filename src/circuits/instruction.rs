@@ -1,91 +1,56 @@
 use halo2_proofs::arithmetic::FieldExt;
 use halo2_proofs::circuit::Layouter;
-use halo2_proofs::plonk::{
-    Column, ConstraintSystem, Error, Expression, Fixed, TableColumn, VirtualCells,
-};
-use num_bigint::BigUint;
-use num_traits::{One, Zero};
-use specs::itable::InstructionTableEntry;
+use halo2_proofs::plonk::{ConstraintSystem, Error, Expression, TableColumn, VirtualCells};
+use specs::itable::{InstructionTableEntry, OPCODE_COLUMNS};
 use std::marker::PhantomData;
-use wasmi::tracer::itable::IEntry;
 
-use crate::circuits::utils::bn_to_field;
-use crate::constant;
+/// Address columns (moid, mmid, fid, bid, iid) followed by the opcode columns.
+pub const INSTRUCTION_COLUMNS: usize = 5 + OPCODE_COLUMNS;
 
-trait Encode {
-    fn encode(&self) -> BigUint;
-    fn encode_addr(&self) -> BigUint;
+pub(crate) trait Encode {
+    fn encode(&self) -> [u64; INSTRUCTION_COLUMNS];
 }
 
 impl Encode for InstructionTableEntry {
-    fn encode(&self) -> BigUint {
-        let opcode: BigUint = self.opcode.clone().into();
-        let mut bn = self.encode_addr();
-        bn <<= 128usize;
-        bn += opcode;
-        bn
-    }
-
-    fn encode_addr(&self) -> BigUint {
-        let mut bn = BigUint::zero();
-        bn += self.moid;
-        bn <<= 16u8;
-        bn += self.mmid;
-        bn <<= 16u8;
-        bn += self.fid;
-        bn <<= 16u8;
-        bn += self.bid;
-        bn <<= 16u8;
-        bn += self.iid;
-        bn
-    }
-}
+    fn encode(&self) -> [u64; INSTRUCTION_COLUMNS] {
+        let opcode: [u64; OPCODE_COLUMNS] = self.opcode.clone().into();
+        let mut columns = [0; INSTRUCTION_COLUMNS];
 
-pub fn encode_inst_expr<F: FieldExt>(
-    moid: Expression<F>,
-    mmid: Expression<F>,
-    fid: Expression<F>,
-    bid: Expression<F>,
-    iid: Expression<F>,
-    opcode: Expression<F>,
-) -> Expression<F> {
-    let mut bn = BigUint::one();
-    let mut acc = opcode;
-    bn <<= 64u8;
-    acc = acc + iid * constant!(bn_to_field(&bn));
-    bn <<= 16u8;
-    acc = acc + bid * constant!(bn_to_field(&bn));
-    bn <<= 16u8;
-    acc = acc + fid * constant!(bn_to_field(&bn));
-    bn <<= 16u8;
-    acc = acc + mmid * constant!(bn_to_field(&bn));
-    bn <<= 16u8;
-    acc = acc + moid * constant!(bn_to_field(&bn));
+        columns[..5].copy_from_slice(&[
+            self.moid as u64,
+            self.mmid as u64,
+            self.fid as u64,
+            self.bid as u64,
+            self.iid as u64,
+        ]);
+        columns[5..].copy_from_slice(&opcode);
 
-    acc
+        columns
+    }
 }
 
 #[derive(Clone)]
 pub struct InstructionConfig<F: FieldExt> {
-    col: TableColumn,
+    cols: [TableColumn; INSTRUCTION_COLUMNS],
     _mark: PhantomData<F>,
 }
 
 impl<F: FieldExt> InstructionConfig<F> {
-    pub fn configure(col: TableColumn) -> InstructionConfig<F> {
+    pub fn configure(cols: [TableColumn; INSTRUCTION_COLUMNS]) -> InstructionConfig<F> {
         InstructionConfig {
-            col,
+            cols,
             _mark: PhantomData,
         }
     }
 
+    /// `expr` gives one expression per column, see `Encode`.
     pub fn configure_in_table(
         &self,
         meta: &mut ConstraintSystem<F>,
         key: &'static str,
-        expr: impl FnOnce(&mut VirtualCells<'_, F>) -> Expression<F>,
+        expr: impl FnOnce(&mut VirtualCells<'_, F>) -> [Expression<F>; INSTRUCTION_COLUMNS],
     ) {
-        meta.lookup(key, |meta| vec![(expr(meta), self.col)]);
+        meta.lookup(key, |meta| expr(meta).into_iter().zip(self.cols).collect());
     }
 }
 
@@ -107,13 +72,20 @@ impl<F: FieldExt> InstructionChip<F> {
         layouter.assign_table(
             || "itable",
             |mut table| {
-                for (i, v) in instructions.iter().enumerate() {
-                    table.assign_cell(
-                        || "init instruction table",
-                        self.config.col,
-                        i,
-                        || Ok(bn_to_field::<F>(&v.encode())),
-                    )?;
+                // the zero entry keeps disabled lookups satisfied
+                let rows = [[0; INSTRUCTION_COLUMNS]]
+                    .into_iter()
+                    .chain(instructions.iter().map(|v| v.encode()));
+
+                for (i, row) in rows.enumerate() {
+                    for (col, value) in self.config.cols.iter().zip(row) {
+                        table.assign_cell(
+                            || "init instruction table",
+                            *col,
+                            i,
+                            || Ok(F::from(value)),
+                        )?;
+                    }
                 }
 
                 Ok(())
@@ -0,0 +1,136 @@
+use halo2_proofs::arithmetic::FieldExt;
+use halo2_proofs::plonk::{Column, ConstraintSystem, Expression, Instance, VirtualCells};
+use halo2_proofs::poly::Rotation;
+use lazy_static::lazy_static;
+use num_bigint::BigUint;
+use specs::mtable::VarType;
+use specs::segment::{argument_vtype, SegmentBoundary};
+use specs::types::Value;
+use std::marker::PhantomData;
+
+use crate::circuits::utils::bn_to_field;
+use crate::constant;
+
+lazy_static! {
+    static ref VTYPE_SHIFT: BigUint = BigUint::from(1u64) << 64;
+    static ref INDEX_SHIFT: BigUint = BigUint::from(1u64) << 72;
+    static ref IO_TYPE_SHIFT: BigUint = BigUint::from(1u64) << 88;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IOType {
+    Argument = 1,
+    Return = 2,
+    /// Fuel consumed by the events, with `CircuitOptions::fuel_metering` only.
+    Fuel = 3,
+}
+
+/// `vtype` is zero for untyped values, i.e. the fuel.
+pub fn encode_io(iotype: IOType, index: u64, vtype: u64, value: u64) -> BigUint {
+    let mut bn = BigUint::from(iotype as u64);
+    bn <<= 16;
+    bn += index;
+    bn <<= 8;
+    bn += vtype;
+    bn <<= 64;
+    bn += value;
+    bn
+}
+
+fn encode_value(iotype: IOType, index: u64, vtype: VarType, value: &Value) -> BigUint {
+    encode_io(iotype, index, vtype as u64, value.raw())
+}
+
+/// Public inputs of an execution: every argument and every returned value is
+/// encoded as one instance cell, `iotype << 88 | index << 72 | vtype << 64 | value`,
+/// followed by the consumed fuel when the circuit meters it.
+pub fn instances_of<F: FieldExt>(
+    arguments: &[Value],
+    returns: &[Value],
+    fuel: Option<u64>,
+) -> Vec<F> {
+    let arguments = arguments
+        .iter()
+        .enumerate()
+        .map(|(i, v)| encode_value(IOType::Argument, i as u64, argument_vtype(v), v));
+    let returns = returns
+        .iter()
+        .enumerate()
+        .map(|(i, v)| encode_value(IOType::Return, i as u64, v.into(), v));
+    let fuel = fuel.map(|fuel| encode_io(IOType::Fuel, 0, 0, fuel));
+
+    arguments
+        .chain(returns)
+        .chain(fuel)
+        .map(|bn| bn_to_field(&bn))
+        .collect()
+}
+
+/// Public inputs of a segment, the stack it starts with takes the place of the
+/// arguments and is keyed by stack offset.
+pub fn segment_instances_of<F: FieldExt>(
+    boundary: &SegmentBoundary,
+    fuel_metering: bool,
+) -> Vec<F> {
+    let arguments = boundary.start_stack.iter().map(|entry| {
+        encode_io(
+            IOType::Argument,
+            entry.offset,
+            entry.vtype as u64,
+            entry.value,
+        )
+    });
+    let returns = boundary
+        .returns
+        .iter()
+        .enumerate()
+        .map(|(i, v)| encode_value(IOType::Return, i as u64, v.into(), v));
+    let fuel = fuel_metering.then(|| encode_io(IOType::Fuel, 0, 0, boundary.fuel));
+
+    arguments
+        .chain(returns)
+        .chain(fuel)
+        .map(|bn| bn_to_field(&bn))
+        .collect()
+}
+
+/// The instance column is used as a lookup table, rows without public input
+/// are zero so that disabled lookups (`expr == 0`) always hit.
+#[derive(Clone)]
+pub struct IOConfig<F: FieldExt> {
+    col: Column<Instance>,
+    _mark: PhantomData<F>,
+}
+
+impl<F: FieldExt> IOConfig<F> {
+    pub fn configure(col: Column<Instance>) -> IOConfig<F> {
+        IOConfig {
+            col,
+            _mark: PhantomData,
+        }
+    }
+
+    pub fn encode(
+        &self,
+        iotype: Expression<F>,
+        index: Expression<F>,
+        vtype: Expression<F>,
+        value: Expression<F>,
+    ) -> Expression<F> {
+        iotype * constant!(bn_to_field(&IO_TYPE_SHIFT))
+            + index * constant!(bn_to_field(&INDEX_SHIFT))
+            + vtype * constant!(bn_to_field(&VTYPE_SHIFT))
+            + value
+    }
+
+    pub fn configure_in_table(
+        &self,
+        meta: &mut ConstraintSystem<F>,
+        key: &'static str,
+        expr: impl FnOnce(&mut VirtualCells<'_, F>) -> Expression<F>,
+    ) {
+        meta.lookup_any(key, |meta| {
+            vec![(expr(meta), meta.query_instance(self.col, Rotation::cur()))]
+        });
+    }
+}
@@ -1,6 +1,7 @@
 use halo2_proofs::arithmetic::FieldExt;
 use halo2_proofs::plonk::{Advice, Column, Error};
 use num_bigint::BigUint;
+use num_traits::Zero;
 use specs::jtable::JumpTableEntry;
 use std::marker::PhantomData;
 
@@ -9,7 +10,21 @@ use crate::circuits::Encode;
 
 impl Encode for JumpTableEntry {
     fn encode(&self) -> BigUint {
-        todo!()
+        let mut bn = BigUint::zero();
+        bn += self.eid;
+        bn <<= 64;
+        bn += self.last_jump_eid;
+        for field in [
+            self.inst.moid,
+            self.inst.mmid,
+            self.inst.fid,
+            self.inst.bid,
+            self.inst.iid,
+        ] {
+            bn <<= 16;
+            bn += field;
+        }
+        bn
     }
 }
 
@@ -41,18 +56,63 @@ impl<F: FieldExt> JumpChip<F> {
         }
     }
 
-    pub fn add_jump(
-        &self,
-        ctx: &mut Context<'_, F>,
-        jump: Box<JumpTableEntry>,
-    ) -> Result<(), Error> {
-        ctx.region.assign_advice_from_constant(
+    pub fn add_jump(&self, ctx: &mut Context<'_, F>, jump: &JumpTableEntry) -> Result<(), Error> {
+        ctx.region.assign_advice(
             || "jump table entry",
             self.config.col,
             ctx.offset,
-            bn_to_field(&jump.encode()),
+            || Ok(bn_to_field(&jump.encode())),
         )?;
 
         Ok(())
     }
+
+    pub fn assign(
+        &self,
+        ctx: &mut Context<'_, F>,
+        entries: &[JumpTableEntry],
+    ) -> Result<(), Error> {
+        for entry in entries {
+            self.add_jump(ctx, entry)?;
+            ctx.next();
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::itable::{InstructionTableEntry, Opcode};
+    use specs::jtable::JumpTableEntry;
+    use std::collections::HashSet;
+
+    use crate::circuits::Encode;
+
+    #[test]
+    fn test_encode_distinct_fields() {
+        let entry = |eid, last_jump_eid, fid, iid| JumpTableEntry {
+            eid,
+            last_jump_eid,
+            inst: Box::new(InstructionTableEntry {
+                moid: 0,
+                mmid: 0,
+                fid,
+                bid: 0,
+                iid,
+                opcode: Opcode::Drop,
+            }),
+        };
+
+        let entries = [
+            entry(1, 0, 0, 0),
+            entry(0, 1, 0, 0),
+            entry(0, 0, 1, 0),
+            entry(0, 0, 0, 1),
+            entry(u64::MAX, u64::MAX, u16::MAX, u16::MAX),
+        ];
+        let encoded = entries.iter().map(|e| e.encode()).collect::<HashSet<_>>();
+
+        assert_eq!(encoded.len(), entries.len());
+    }
 }
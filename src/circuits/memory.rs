@@ -1,15 +1,18 @@
 use halo2_proofs::arithmetic::FieldExt;
-use halo2_proofs::circuit::Cell;
+use halo2_proofs::circuit::AssignedCell;
 use halo2_proofs::plonk::{Advice, Column, ConstraintSystem, Error, Expression, VirtualCells};
 use halo2_proofs::poly::Rotation;
 use lazy_static::lazy_static;
 use num_bigint::BigUint;
+use rayon::prelude::*;
 use specs::mtable::{AccessType, LocationType, MemoryTableEntry, VarType};
 use std::marker::PhantomData;
 
+use crate::circuits::io::{IOConfig, IOType};
 use crate::circuits::memory_init::InitMemoryConfig;
 use crate::circuits::range::RangeConfig;
-use crate::circuits::utils::row_diff::RowDiffConfig;
+use crate::circuits::state::StateConfig;
+use crate::circuits::utils::row_diff::{RowDiffConfig, RowDiffWitness};
 use crate::circuits::utils::{bn_to_field, Context};
 use crate::{constant, constant_from, cur, next, pre};
 
@@ -23,6 +26,15 @@ lazy_static! {
     static ref EID_SHIFT: BigUint = BigUint::from(1u64) << 128;
 }
 
+struct MemoryRow<F: FieldExt> {
+    eid: RowDiffWitness<F>,
+    emid: RowDiffWitness<F>,
+    mmid: RowDiffWitness<F>,
+    offset: RowDiffWitness<F>,
+    ltype: RowDiffWitness<F>,
+    same_location: bool,
+}
+
 #[derive(Clone)]
 pub struct MemoryConfig<F: FieldExt> {
     eid: RowDiffConfig<F>,
@@ -38,30 +50,50 @@ pub struct MemoryConfig<F: FieldExt> {
     same_location: Column<Advice>,
     enable: Column<Advice>,
     rest_mops: Column<Advice>,
-
+    /// Stack init entries of the remaining rows, the first row holds their number.
+    rest_inits: Column<Advice>,
+    /// Like `rest_inits`, for the stack locations committed as the end stack.
+    rest_ends: Column<Advice>,
+    /// One on every row when a segment follows, its end stack is then public.
+    has_end: Column<Advice>,
+
+    state: StateConfig<F>,
     _mark: PhantomData<F>,
 }
 
 impl<F: FieldExt> MemoryConfig<F> {
-    /// RowDiffConfig needs 3 cols. 3 * 5 + 5 = 20
+    /// RowDiffConfig needs 3 cols. 3 * 5 + 9 = 24
     ///
-    /// Now MemoryConfig needs 20 cols.
+    /// Now MemoryConfig needs 24 cols.
     pub fn new(
         meta: &mut ConstraintSystem<F>,
         cols: &mut impl Iterator<Item = Column<Advice>>,
+        state: &StateConfig<F>,
     ) -> MemoryConfig<F> {
-        let emid = RowDiffConfig::configure("mtable emid", meta, cols, |_| constant_from!(1));
-        let ltype = RowDiffConfig::configure("mtable ltype", meta, cols, |_| constant_from!(1));
-        let mmid = RowDiffConfig::configure("mtable mmid", meta, cols, |_| constant_from!(1));
-        let offset = RowDiffConfig::configure("mtable offset", meta, cols, |_| constant_from!(1));
-        let eid = RowDiffConfig::configure("mtable eid", meta, cols, |_| constant_from!(1));
+        let enable = cols.next().unwrap();
+        // the first row has no previous row to be compared with
+        let has_pre = |meta: &mut VirtualCells<'_, F>| {
+            state.sel(meta) * (constant_from!(1) - state.first(meta)) * cur!(meta, enable)
+        };
+        let emid = RowDiffConfig::configure("mtable emid", meta, cols, &has_pre);
+        let ltype = RowDiffConfig::configure("mtable ltype", meta, cols, &has_pre);
+        let mmid = RowDiffConfig::configure("mtable mmid", meta, cols, &has_pre);
+        let offset = RowDiffConfig::configure("mtable offset", meta, cols, &has_pre);
+        let eid = RowDiffConfig::configure("mtable eid", meta, cols, &has_pre);
 
         let value = cols.next().unwrap();
         let atype = cols.next().unwrap();
         let vtype = cols.next().unwrap();
-        let enable = cols.next().unwrap();
         let same_location = cols.next().unwrap();
         let rest_mops = cols.next().unwrap();
+        let rest_inits = cols.next().unwrap();
+        let rest_ends = cols.next().unwrap();
+        let has_end = cols.next().unwrap();
+
+        meta.enable_equality(rest_mops);
+        meta.enable_equality(rest_inits);
+        meta.enable_equality(rest_ends);
+        meta.enable_equality(has_end);
 
         MemoryConfig {
             ltype,
@@ -75,6 +107,10 @@ impl<F: FieldExt> MemoryConfig<F> {
             enable,
             same_location,
             rest_mops,
+            rest_inits,
+            rest_ends,
+            has_end,
+            state: state.clone(),
             _mark: PhantomData,
         }
     }
@@ -109,11 +145,11 @@ impl<F: FieldExt> MemoryConfig<F> {
                     + constant!(bn_to_field(&LOC_TYPE_SHIFT))
                         * constant_from!(LocationType::Stack)
                     + constant!(bn_to_field(&ACCESS_TYPE_SHIFT))
-                        * constant_from!(AccessType::Write)
+                        * constant_from!(AccessType::Read)
                     + vtype(meta) * constant!(bn_to_field(&VAR_TYPE_SHIFT))
                     + value(meta))
                     * enable(meta),
-                self.encode_for_lookup(meta) * enable(meta),
+                self.encode_for_lookup(meta) * self.is_enable(meta),
             )]
         });
     }
@@ -141,7 +177,7 @@ impl<F: FieldExt> MemoryConfig<F> {
                     + vtype(meta) * constant!(bn_to_field(&VAR_TYPE_SHIFT))
                     + value(meta))
                     * enable(meta),
-                self.encode_for_lookup(meta) * enable(meta),
+                self.encode_for_lookup(meta) * self.is_enable(meta),
             )]
         });
     }
@@ -151,8 +187,10 @@ impl<F: FieldExt> MemoryConfig<F> {
         cols: &mut impl Iterator<Item = Column<Advice>>,
         range: &RangeConfig<F>,
         memory_init: &InitMemoryConfig<F>,
+        io: &IOConfig<F>,
+        state: &StateConfig<F>,
     ) -> MemoryConfig<F> {
-        let memory = Self::new(meta, cols);
+        let memory = Self::new(meta, cols, state);
 
         memory.configure_enable(meta);
         memory.configure_sort(meta, range);
@@ -160,20 +198,23 @@ impl<F: FieldExt> MemoryConfig<F> {
         memory.configure_range(meta, range);
         memory.configure_same_location(meta);
         memory.configure_rule(meta, memory_init);
+        memory.configure_arguments(meta, io);
+        memory.configure_stack_end(meta, state);
 
         memory
     }
 
     fn configure_enable(&self, meta: &mut ConstraintSystem<F>) -> &MemoryConfig<F> {
         meta.create_gate("enable seq", |meta| {
+            let sel = self.state.sel(meta);
             let cur = cur!(meta, self.enable);
             let next = next!(meta, self.enable);
 
             // next * (cur - 1) == 0
             // cur  * (cur - 1) == 0
             vec![
-                next * (cur.clone() - Expression::Constant(F::one())),
-                cur.clone() * (cur.clone() - Expression::Constant(F::one())),
+                sel.clone() * next * (cur.clone() - Expression::Constant(F::one())),
+                sel * cur.clone() * (cur.clone() - Expression::Constant(F::one())),
             ]
         });
 
@@ -184,9 +225,14 @@ impl<F: FieldExt> MemoryConfig<F> {
         meta.create_gate("is same location", |meta| {
             let same_location = cur!(meta, self.same_location);
 
+            // neither the first row nor padding rows continue a location
             vec![
-                self.ltype.is_same(meta) * self.mmid.is_same(meta) * self.offset.is_same(meta)
-                    - same_location,
+                self.state.sel(meta)
+                    * (self.has_pre(meta)
+                        * self.ltype.is_same(meta)
+                        * self.mmid.is_same(meta)
+                        * self.offset.is_same(meta)
+                        - same_location),
             ]
         });
 
@@ -197,7 +243,7 @@ impl<F: FieldExt> MemoryConfig<F> {
         meta.create_gate("stack_or_heap", |meta| {
             let ltype = self.ltype.data(meta);
 
-            vec![ltype.clone() * (ltype - Expression::Constant(F::one()))]
+            vec![self.state.sel(meta) * ltype.clone() * (ltype - Expression::Constant(F::one()))]
         });
 
         self
@@ -213,7 +259,7 @@ impl<F: FieldExt> MemoryConfig<F> {
         range.configure_in_common_range(meta, "eid in range", |meta| self.eid.data(meta));
 
         range.configure_in_common_range(meta, "emid in range", |meta| self.emid.data(meta));
-        range.configure_in_common_range(meta, "vtype in range", |meta| self.emid.data(meta));
+        range.configure_in_common_range(meta, "vtype in range", |meta| cur!(meta, self.vtype));
 
         self
     }
@@ -224,16 +270,16 @@ impl<F: FieldExt> MemoryConfig<F> {
         range: &RangeConfig<F>,
     ) -> &MemoryConfig<F> {
         range.configure_in_common_range(meta, "ltype sort", |meta| {
-            self.is_enable(meta) * self.ltype.diff(meta)
+            self.has_pre(meta) * self.ltype.diff(meta)
         });
         range.configure_in_common_range(meta, "mmid sort", |meta| {
-            self.is_enable(meta) * self.ltype.is_same(meta) * self.mmid.diff(meta)
+            self.has_pre(meta) * self.ltype.is_same(meta) * self.mmid.diff(meta)
         });
         range.configure_in_common_range(meta, "offset sort", |meta| {
-            self.is_enable(meta)
+            self.has_pre(meta)
                 * self.ltype.is_same(meta)
                 * self.mmid.is_same(meta)
-                * self.offset.is_same(meta)
+                * self.offset.diff(meta)
         });
         range.configure_in_common_range(meta, "eid sort", |meta| {
             self.is_enable(meta) * self.is_same_location(meta) * self.eid.diff(meta)
@@ -253,23 +299,36 @@ impl<F: FieldExt> MemoryConfig<F> {
         meta: &mut ConstraintSystem<F>,
         memory_init: &InitMemoryConfig<F>,
     ) -> &MemoryConfig<F> {
+        // a read of a new location is rejected by its first line
         meta.create_gate("memory read after write", |meta| {
+            let enable =
+                self.state.sel(meta) * self.is_same_location(meta) * self.is_read_not_bit(meta);
+
             vec![
-                self.is_enable(meta) * self.is_read_not_bit(meta) * self.diff(meta, self.value),
-                self.is_enable(meta) * self.is_read_not_bit(meta) * self.diff(meta, self.vtype),
+                enable.clone() * self.diff(meta, self.value),
+                enable * self.diff(meta, self.vtype),
             ]
         });
 
+        // emid restarts with every event
         meta.create_gate("memory emid unique", |meta| {
-            vec![self.is_enable(meta) * self.is_same_location(meta) * self.emid.is_same(meta)]
+            vec![
+                self.state.sel(meta)
+                    * self.is_enable(meta)
+                    * self.is_same_location(meta)
+                    * self.eid.is_same(meta)
+                    * self.emid.is_same(meta),
+            ]
         });
 
         meta.create_gate("memory stack first line", |meta| {
             vec![
-                self.is_enable(meta)
+                self.state.sel(meta)
+                    * self.is_enable(meta)
                     * (self.is_same_location(meta) - Expression::Constant(F::one()))
                     * self.is_stack(meta)
-                    * (cur!(meta, self.atype) - constant_from!(AccessType::Write)),
+                    * (cur!(meta, self.atype) - constant_from!(AccessType::Write))
+                    * (cur!(meta, self.atype) - constant_from!(AccessType::Init)),
             ]
         });
 
@@ -285,19 +344,120 @@ impl<F: FieldExt> MemoryConfig<F> {
                 )
         });
 
-        memory_init.configure_in_table(meta, "rest mops decrease", |meta| {
+        // init entries are not operations of the events
+        meta.create_gate("rest mops decrease", |meta| {
+            let sel = self.state.sel(meta);
+
+            vec![
+                sel.clone()
+                    * self.is_enable(meta)
+                    * (cur!(meta, self.rest_mops)
+                        - next!(meta, self.rest_mops)
+                        - self.is_not_init(meta)),
+                sel * (self.is_enable(meta) - constant_from!(1)) * cur!(meta, self.rest_mops),
+            ]
+        });
+
+        self
+    }
+
+    fn configure_arguments(
+        &self,
+        meta: &mut ConstraintSystem<F>,
+        io: &IOConfig<F>,
+    ) -> &MemoryConfig<F> {
+        // init entries come before the events, each at its own location
+        meta.create_gate("memory init entries", |meta| {
+            let enable = self.state.sel(meta) * self.is_enable(meta) * self.is_init(meta);
+
+            vec![
+                enable.clone() * self.eid.data(meta),
+                enable.clone() * self.is_same_location(meta),
+                enable * self.is_stack(meta) * self.mmid.data(meta),
+            ]
+        });
+
+        // stack init entries are the arguments of the invoked function
+        io.configure_in_table(meta, "memory stack init in instance", |meta| {
             self.is_enable(meta)
-                * self.is_not_init(meta)
-                * (cur!(meta, self.rest_mops) - next!(meta, self.rest_mops) - constant_from!(1))
+                * self.is_init(meta)
+                * self.is_stack(meta)
+                * io.encode(
+                    constant_from!(IOType::Argument),
+                    self.offset.data(meta),
+                    cur!(meta, self.vtype),
+                    cur!(meta, self.value),
+                )
         });
 
-        memory_init.configure_in_table(meta, "rest mop zero when disabled", |meta| {
-            (self.is_enable(meta) - constant_from!(1)) * cur!(meta, self.rest_mops)
+        // Their number is public, so that with distinct offsets every argument
+        // has an entry.
+        meta.create_gate("rest inits decrease", |meta| {
+            let sel = self.state.sel(meta);
+
+            vec![
+                sel.clone()
+                    * self.is_enable(meta)
+                    * (cur!(meta, self.rest_inits)
+                        - next!(meta, self.rest_inits)
+                        - self.is_init(meta) * self.is_stack(meta)),
+                sel * (self.is_enable(meta) - constant_from!(1)) * cur!(meta, self.rest_inits),
+            ]
         });
 
         self
     }
 
+    fn configure_stack_end(
+        &self,
+        meta: &mut ConstraintSystem<F>,
+        state: &StateConfig<F>,
+    ) -> &MemoryConfig<F> {
+        meta.create_gate("memory has_end is the same on every row", |meta| {
+            vec![
+                self.state.sel(meta)
+                    * next!(meta, self.enable)
+                    * (next!(meta, self.has_end) - cur!(meta, self.has_end)),
+            ]
+        });
+
+        // the last entry of a stack location is its value at the end of the segment
+        state.configure_in_table(meta, "memory stack end in instance", |meta| {
+            self.is_enable(meta)
+                * self.is_stack_end(meta)
+                * state.encode_stack(
+                    self.mmid.data(meta),
+                    self.offset.data(meta),
+                    cur!(meta, self.vtype),
+                    cur!(meta, self.value),
+                )
+        });
+
+        // Their number is public, the end stack has no other location since
+        // locations are distinct.
+        meta.create_gate("rest ends decrease", |meta| {
+            let sel = self.state.sel(meta);
+
+            vec![
+                sel.clone()
+                    * self.is_enable(meta)
+                    * (cur!(meta, self.rest_ends)
+                        - next!(meta, self.rest_ends)
+                        - self.is_stack_end(meta)),
+                sel * (self.is_enable(meta) - constant_from!(1)) * cur!(meta, self.rest_ends),
+            ]
+        });
+
+        self
+    }
+
+    /// Last entry of a stack location when a segment follows.
+    fn is_stack_end(&self, meta: &mut VirtualCells<F>) -> Expression<F> {
+        cur!(meta, self.has_end)
+            * (constant_from!(1) - next!(meta, self.same_location))
+            * self.is_stack(meta)
+    }
+
     fn is_heap(&self, meta: &mut VirtualCells<F>) -> Expression<F> {
         Expression::Constant(F::one()) - self.ltype.data(meta)
     }
@@ -329,6 +489,16 @@ impl<F: FieldExt> MemoryConfig<F> {
                 * constant!(((write_f - read_f) * (write_f - init_f)).invert().unwrap())
     }
 
+    fn is_init(&self, meta: &mut VirtualCells<F>) -> Expression<F> {
+        let read_f = F::from(AccessType::Read as u64);
+        let write_f = F::from(AccessType::Write as u64);
+        let init_f = F::from(AccessType::Init as u64);
+        let atype = cur!(meta, self.atype);
+        (atype.clone() - constant_from!(AccessType::Read))
+            * (atype - constant_from!(AccessType::Write))
+            * constant!(((init_f - read_f) * (init_f - write_f)).invert().unwrap())
+    }
+
     fn is_same_location(&self, meta: &mut VirtualCells<F>) -> Expression<F> {
         cur!(meta, self.same_location)
     }
@@ -336,6 +506,17 @@ impl<F: FieldExt> MemoryConfig<F> {
     fn is_enable(&self, meta: &mut VirtualCells<F>) -> Expression<F> {
         cur!(meta, self.enable)
     }
+
+    /// Enabled rows but the first one, whose previous row is not in the table.
+    fn has_pre(&self, meta: &mut VirtualCells<F>) -> Expression<F> {
+        (constant_from!(1) - self.state.first(meta)) * self.is_enable(meta)
+    }
+}
+
+pub struct MemoryTableCells<F: FieldExt> {
+    pub has_end: AssignedCell<F, F>,
+    pub inits: AssignedCell<F, F>,
+    pub ends: AssignedCell<F, F>,
 }
 
 pub struct MemoryChip<F: FieldExt> {
@@ -355,92 +536,155 @@ impl<F: FieldExt> MemoryChip<F> {
         &self,
         ctx: &mut Context<'_, F>,
         entries: &Vec<MemoryTableEntry>,
-        etable_rest_mops_cell: Cell,
-    ) -> Result<(), Error> {
+        etable_rest_mops: &AssignedCell<F, F>,
+        has_end: bool,
+    ) -> Result<MemoryTableCells<F>, Error> {
+        // the witness only depends on the previous entry, rows are computed in parallel
+        let rows = entries
+            .par_iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let last_entry = i.checked_sub(1).map(|i| &entries[i]);
+                let row_diff = |field: fn(&MemoryTableEntry) -> u64| {
+                    let data = F::from(field(entry));
+                    let last = F::from(last_entry.map_or(0, field));
+                    RowDiffWitness::new(data, data - last)
+                };
+
+                MemoryRow {
+                    eid: row_diff(|e| e.eid),
+                    emid: row_diff(|e| e.emid),
+                    mmid: row_diff(|e| e.mmid),
+                    offset: row_diff(|e| e.offset),
+                    ltype: row_diff(|e| e.ltype as u64),
+                    same_location: last_entry.map_or(false, |last| last.is_same_location(entry)),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut has_end_cell = None;
+        let mut inits_cell = None;
         let mut mops = entries.iter().fold(0, |acc, e| {
             acc + if e.atype == AccessType::Init { 0 } else { 1 }
         });
-        let mut last_entry: Option<&MemoryTableEntry> = None;
-        for (i, entry) in entries.into_iter().enumerate() {
-            macro_rules! row_diff_assign {
-                ($x: ident) => {
-                    self.config.$x.assign(
-                        ctx,
-                        (entry.$x as u64).into(),
-                        ((entry.$x as u64)
-                            - last_entry.as_ref().map(|x| x.$x as u64).unwrap_or(0u64))
-                        .into(),
-                    )?;
-                };
+        let is_stack_init =
+            |e: &MemoryTableEntry| e.atype == AccessType::Init && e.ltype == LocationType::Stack;
+        let mut inits = entries.iter().filter(|e| is_stack_init(e)).count() as u64;
+        let is_stack_end = |i: usize| {
+            has_end
+                && entries[i].ltype == LocationType::Stack
+                && entries
+                    .get(i + 1)
+                    .map_or(true, |next| !entries[i].is_same_location(next))
+        };
+        let mut ends = (0..entries.len()).filter(|i| is_stack_end(*i)).count() as u64;
+        let mut ends_cell = None;
+        for (i, (entry, row)) in entries.iter().zip(rows).enumerate() {
+            self.config.eid.assign_witness(ctx, &row.eid)?;
+            self.config.emid.assign_witness(ctx, &row.emid)?;
+            self.config.mmid.assign_witness(ctx, &row.mmid)?;
+            self.config.offset.assign_witness(ctx, &row.offset)?;
+            self.config.ltype.assign_witness(ctx, &row.ltype)?;
+
+            for (name, col, value) in [
+                ("memory atype", self.config.atype, entry.atype as u64),
+                ("memory vtype", self.config.vtype, entry.vtype as u64),
+                ("memory value", self.config.value, entry.value),
+                ("memory enable", self.config.enable, 1),
+                (
+                    "memory same_location",
+                    self.config.same_location,
+                    row.same_location as u64,
+                ),
+            ] {
+                ctx.region
+                    .assign_advice(|| name, col, ctx.offset, || Ok(F::from(value)))?;
             }
 
-            row_diff_assign!(eid);
-            row_diff_assign!(emid);
-            row_diff_assign!(mmid);
-            row_diff_assign!(offset);
-            row_diff_assign!(ltype);
-
-            ctx.region.assign_advice(
-                || "memory atype",
-                self.config.atype,
-                ctx.offset,
-                || Ok((entry.atype as u64).into()),
-            )?;
-
-            ctx.region.assign_advice(
-                || "memory vtype",
-                self.config.vtype,
+            // the flag of the first row is bound to the state instance
+            let cell = ctx.region.assign_advice(
+                || "memory has_end",
+                self.config.has_end,
                 ctx.offset,
-                || Ok((entry.vtype as u64).into()),
+                || Ok(F::from(has_end as u64)),
             )?;
 
-            ctx.region.assign_advice(
-                || "memory value",
-                self.config.value,
+            let inits_assigned = ctx.region.assign_advice(
+                || "memory rest_inits",
+                self.config.rest_inits,
                 ctx.offset,
-                || Ok((entry.value as u64).into()),
+                || Ok(F::from(inits)),
             )?;
 
-            ctx.region.assign_advice(
-                || "memory enable",
-                self.config.enable,
+            let ends_assigned = ctx.region.assign_advice(
+                || "memory rest_ends",
+                self.config.rest_ends,
                 ctx.offset,
-                || Ok(F::one()),
+                || Ok(F::from(ends)),
             )?;
 
-            let cell = ctx.region.assign_advice(
-                || "memory enable",
-                self.config.rest_mops,
-                ctx.offset,
-                || Ok(F::from(mops)),
-            )?;
             if i == 0 {
-                ctx.region
-                    .constrain_equal(cell.cell(), etable_rest_mops_cell)?;
+                has_end_cell = Some(cell);
+                inits_cell = Some(inits_assigned);
+                ends_cell = Some(ends_assigned);
+                etable_rest_mops.copy_advice(
+                    || "memory rest_mops",
+                    &mut ctx.region,
+                    self.config.rest_mops,
+                    ctx.offset,
+                )?;
+            } else {
+                ctx.region.assign_advice(
+                    || "memory rest_mops",
+                    self.config.rest_mops,
+                    ctx.offset,
+                    || Ok(F::from(mops)),
+                )?;
             }
 
-            ctx.region.assign_advice(
-                || "memory same_location",
-                self.config.same_location,
-                ctx.offset,
-                || {
-                    Ok(last_entry.as_ref().map_or(F::zero(), |last_entry| {
-                        if last_entry.is_same_location(&entry) {
-                            F::one()
-                        } else {
-                            F::zero()
-                        }
-                    }))
-                },
-            )?;
-
             if entry.atype != AccessType::Init {
                 mops -= 1;
             }
-            last_entry = Some(entry);
+            if is_stack_init(entry) {
+                inits -= 1;
+            }
+            if is_stack_end(i) {
+                ends -= 1;
+            }
             ctx.next();
         }
 
-        Ok(())
+        if entries.is_empty() {
+            etable_rest_mops.copy_advice(
+                || "memory rest_mops",
+                &mut ctx.region,
+                self.config.rest_mops,
+                ctx.offset,
+            )?;
+            has_end_cell = Some(ctx.region.assign_advice(
+                || "memory has_end",
+                self.config.has_end,
+                ctx.offset,
+                || Ok(F::from(has_end as u64)),
+            )?);
+            inits_cell = Some(ctx.region.assign_advice(
+                || "memory rest_inits",
+                self.config.rest_inits,
+                ctx.offset,
+                || Ok(F::zero()),
+            )?);
+            ends_cell = Some(ctx.region.assign_advice(
+                || "memory rest_ends",
+                self.config.rest_ends,
+                ctx.offset,
+                || Ok(F::zero()),
+            )?);
+        }
+
+        Ok(MemoryTableCells {
+            has_end: has_end_cell.unwrap(),
+            inits: inits_cell.unwrap(),
+            ends: ends_cell.unwrap(),
+        })
     }
 }
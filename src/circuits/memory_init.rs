@@ -63,19 +63,29 @@ pub struct MemoryInitChip<F: FieldExt> {
 }
 
 impl<F: FieldExt> MemoryInitChip<F> {
+    pub fn new(config: InitMemoryConfig<F>) -> MemoryInitChip<F> {
+        MemoryInitChip {
+            config,
+            _phantom: PhantomData,
+        }
+    }
+
     pub fn add_memory_init(
-        self,
+        &self,
         layouter: &mut impl Layouter<F>,
-        memory_init: Vec<InitMemoryTableEntry>,
+        memory_init: &Vec<InitMemoryTableEntry>,
     ) -> Result<(), Error> {
         layouter.assign_table(
             || "memory_init",
             |mut table| {
+                // the zero entry keeps disabled lookups satisfied
+                table.assign_cell(|| "memory init table", self.config.col, 0, || Ok(F::zero()))?;
+
                 for (i, v) in memory_init.iter().enumerate() {
                     table.assign_cell(
                         || "memory init table",
                         self.config.col,
-                        i,
+                        i + 1,
                         || Ok(bn_to_field::<F>(&v.encode())),
                     )?;
                 }
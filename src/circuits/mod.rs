@@ -2,12 +2,16 @@ use num_bigint::BigUint;
 
 pub mod config_builder;
 pub mod event;
+pub mod image;
 pub mod instruction;
+pub mod io;
 pub mod jump;
 pub mod memory;
 pub mod memory_init;
 pub mod range;
+pub mod state;
 pub mod utils;
+pub mod wasm_circuit;
 
 trait Encode {
     fn encode(&self) -> BigUint;
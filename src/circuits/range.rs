@@ -77,6 +77,15 @@ impl<F: FieldExt> RangeChip<F> {
         }
     }
 
+    /// Rows taken by the longest table assigned in `init`.
+    pub fn rows(range: usize) -> usize {
+        let vtype_byte_rows = (0..8u64)
+            .flat_map(|pos| VarType::iter().map(move |t| if pos < t.byte_size() { 256 } else { 1 }))
+            .sum::<usize>();
+
+        range.max(256).max(vtype_byte_rows)
+    }
+
     pub fn init(&self, layouter: &mut impl Layouter<F>, range: usize) -> Result<(), Error> {
         layouter.assign_table(
             || "common range table",
@@ -97,7 +106,7 @@ impl<F: FieldExt> RangeChip<F> {
         layouter.assign_table(
             || "byte range table",
             |mut table| {
-                for i in 0..255usize {
+                for i in 0..256usize {
                     table.assign_cell(
                         || "range table",
                         self.config.byte_col,
@@ -0,0 +1,186 @@
+use halo2_proofs::arithmetic::FieldExt;
+use halo2_proofs::plonk::{
+    Column, ConstraintSystem, Error, Expression, Fixed, Instance, VirtualCells,
+};
+use halo2_proofs::poly::Rotation;
+use lazy_static::lazy_static;
+use num_bigint::BigUint;
+use specs::mtable::MemoryTableEntry;
+use specs::segment::{MachineState, SegmentBoundary};
+use std::marker::PhantomData;
+
+use crate::circuits::utils::{bn_to_field, Context};
+use crate::constant;
+
+lazy_static! {
+    static ref FID_SHIFT: BigUint = BigUint::from(1u64) << 16;
+    static ref SP_SHIFT: BigUint = BigUint::from(1u64) << 64;
+    static ref EID_SHIFT: BigUint = BigUint::from(1u64) << 128;
+    static ref VTYPE_SHIFT: BigUint = BigUint::from(1u64) << 96;
+    static ref OFFSET_SHIFT: BigUint = BigUint::from(1u64) << 112;
+    static ref MMID_SHIFT: BigUint = BigUint::from(1u64) << 176;
+    static ref STATE_TYPE_SHIFT: BigUint = BigUint::from(1u64) << 192;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StateType {
+    Start = 1,
+    End = 2,
+    Stack = 3,
+}
+
+/// `eid << 128 | sp << 64 | fid << 16 | iid`, without the state type.
+fn encode_registers(state: &MachineState) -> BigUint {
+    BigUint::from(state.eid) * &*EID_SHIFT
+        + BigUint::from(state.sp) * &*SP_SHIFT
+        + BigUint::from(state.fid) * &*FID_SHIFT
+        + state.iid
+}
+
+/// `mmid << 176 | offset << 112 | vtype << 96 | value`, without the state type.
+fn encode_stack(entry: &MemoryTableEntry) -> BigUint {
+    BigUint::from(entry.mmid) * &*MMID_SHIFT
+        + BigUint::from(entry.offset) * &*OFFSET_SHIFT
+        + BigUint::from(entry.vtype as u64) * &*VTYPE_SHIFT
+        + entry.value
+}
+
+/// Public inputs committing to the boundaries of a segment: the first fid of
+/// the image, the registers before the first and after the last event, whether
+/// a segment follows, the sizes of the stack before the first event and of the
+/// stack it continues with, and then that stack. The stack before the first event is committed as
+/// arguments, see `IOType`.
+///
+/// Nothing follows the last segment, so its column only depends on the image
+/// and the number of arguments and returned values.
+pub fn state_instances<F: FieldExt>(first_fid: u16, boundary: &SegmentBoundary) -> Vec<F> {
+    let start = BigUint::from(StateType::Start as u64) * &*STATE_TYPE_SHIFT
+        + encode_registers(&boundary.start);
+    let end = BigUint::from(StateType::End as u64) * &*STATE_TYPE_SHIFT
+        + encode_registers(&boundary.end_state());
+    let has_end = BigUint::from(boundary.end.is_some() as u64);
+    let arguments = BigUint::from(boundary.start_stack.len());
+    let end_stack = if boundary.end.is_some() {
+        &boundary.end_stack[..]
+    } else {
+        &[]
+    };
+    let end_stack_len = BigUint::from(end_stack.len());
+    let stack = end_stack.iter().map(|entry| {
+        BigUint::from(StateType::Stack as u64) * &*STATE_TYPE_SHIFT + encode_stack(entry)
+    });
+
+    [
+        BigUint::from(first_fid),
+        start,
+        end,
+        has_end,
+        arguments,
+        end_stack_len,
+    ]
+    .into_iter()
+    .chain(stack)
+    .map(|bn| bn_to_field(&bn))
+    .collect()
+}
+
+/// Like `IOConfig`, the instance column is used as a lookup table. Its first
+/// row is copied from the first fid of the image, so that verifiers can check
+/// where the first segment starts.
+///
+/// The execution tables all start on the first row, so their fixed selectors
+/// live here as well.
+#[derive(Clone)]
+pub struct StateConfig<F: FieldExt> {
+    col: Column<Instance>,
+    first: Column<Fixed>,
+    sel: Column<Fixed>,
+    _mark: PhantomData<F>,
+}
+
+impl<F: FieldExt> StateConfig<F> {
+    pub fn configure(meta: &mut ConstraintSystem<F>, col: Column<Instance>) -> StateConfig<F> {
+        meta.enable_equality(col);
+
+        StateConfig {
+            col,
+            first: meta.fixed_column(),
+            sel: meta.fixed_column(),
+            _mark: PhantomData,
+        }
+    }
+
+    pub fn instance(&self) -> Column<Instance> {
+        self.col
+    }
+
+    /// One on the first row of the execution tables.
+    pub fn first(&self, meta: &mut VirtualCells<'_, F>) -> Expression<F> {
+        meta.query_fixed(self.first, Rotation::cur())
+    }
+
+    pub fn sel_column(&self) -> Column<Fixed> {
+        self.sel
+    }
+
+    /// One on the rows of the execution tables, padding included. Gates must be
+    /// multiplied by it, halo2 checks them on the blinding rows as well.
+    pub fn sel(&self, meta: &mut VirtualCells<'_, F>) -> Expression<F> {
+        meta.query_fixed(self.sel, Rotation::cur())
+    }
+
+    pub fn encode_registers(
+        &self,
+        stype: StateType,
+        eid: Expression<F>,
+        sp: Expression<F>,
+        fid: Expression<F>,
+        iid: Expression<F>,
+    ) -> Expression<F> {
+        constant!(bn_to_field(
+            &(BigUint::from(stype as u64) * &*STATE_TYPE_SHIFT)
+        )) + eid * constant!(bn_to_field(&EID_SHIFT))
+            + sp * constant!(bn_to_field(&SP_SHIFT))
+            + fid * constant!(bn_to_field(&FID_SHIFT))
+            + iid
+    }
+
+    pub fn encode_stack(
+        &self,
+        mmid: Expression<F>,
+        offset: Expression<F>,
+        vtype: Expression<F>,
+        value: Expression<F>,
+    ) -> Expression<F> {
+        constant!(bn_to_field(
+            &(BigUint::from(StateType::Stack as u64) * &*STATE_TYPE_SHIFT)
+        )) + mmid * constant!(bn_to_field(&MMID_SHIFT))
+            + offset * constant!(bn_to_field(&OFFSET_SHIFT))
+            + vtype * constant!(bn_to_field(&VTYPE_SHIFT))
+            + value
+    }
+
+    pub fn configure_in_table(
+        &self,
+        meta: &mut ConstraintSystem<F>,
+        key: &'static str,
+        expr: impl FnOnce(&mut VirtualCells<'_, F>) -> Expression<F>,
+    ) {
+        meta.lookup_any(key, |meta| {
+            vec![(expr(meta), meta.query_instance(self.col, Rotation::cur()))]
+        });
+    }
+
+    /// Assigns the selectors of the `rows` rows of the execution tables, which
+    /// do not depend on the trace, so that keys can be reused.
+    pub fn assign_rows(&self, ctx: &mut Context<'_, F>, rows: usize) -> Result<(), Error> {
+        ctx.region
+            .assign_fixed(|| "state first", self.first, ctx.offset, || Ok(F::one()))?;
+        for row in 0..rows {
+            ctx.region
+                .assign_fixed(|| "state sel", self.sel, ctx.offset + row, || Ok(F::one()))?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+use halo2_proofs::arithmetic::FieldExt;
+use halo2_proofs::plonk::{Advice, Column, ConstraintSystem};
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub struct ColumnExhausted {
+    pub required: usize,
+    pub available: usize,
+}
+
+impl fmt::Display for ColumnExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "circuit requires {} advice columns, but only {} are allocated",
+            self.required, self.available
+        )
+    }
+}
+
+/// Advice columns taken by each table and in total.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ColumnUsage {
+    pub tables: BTreeMap<&'static str, usize>,
+    pub required: usize,
+    pub available: usize,
+}
+
+impl ColumnUsage {
+    pub fn check(&self) -> Result<(), ColumnExhausted> {
+        if self.required > self.available {
+            Err(ColumnExhausted {
+                required: self.required,
+                available: self.available,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Hands out advice columns to the tables, a column is never handed out twice
+/// unless the tables share it.
+///
+/// `configure` cannot fail, so the tables are first configured with a
+/// `counting` allocator on a scratch constraint system, whose usage is checked
+/// with `ColumnUsage::check`, then with an allocator of the counted columns,
+/// which cannot be created over the budget.
+pub struct ColumnAllocator {
+    cols: Vec<Column<Advice>>,
+    next: usize,
+    counting: bool,
+    usage: ColumnUsage,
+}
+
+/// Columns shared by tables which never use the same rows, see
+/// `ColumnAllocator::shared`.
+pub struct SharedColumns {
+    start: usize,
+    end: usize,
+}
+
+impl ColumnAllocator {
+    /// Allocates `required` columns, as counted by a `counting` allocator,
+    /// out of a budget of `available` ones.
+    pub fn new<F: FieldExt>(
+        meta: &mut ConstraintSystem<F>,
+        available: usize,
+        required: usize,
+    ) -> Result<ColumnAllocator, ColumnExhausted> {
+        let usage = ColumnUsage {
+            available,
+            required,
+            ..Default::default()
+        };
+        usage.check()?;
+
+        Ok(ColumnAllocator {
+            cols: (0..required).map(|_| meta.advice_column()).collect(),
+            next: 0,
+            counting: false,
+            usage: ColumnUsage {
+                available,
+                ..Default::default()
+            },
+        })
+    }
+
+    /// Only counts the columns, every table gets the same column.
+    pub fn counting<F: FieldExt>(
+        meta: &mut ConstraintSystem<F>,
+        available: usize,
+    ) -> ColumnAllocator {
+        ColumnAllocator {
+            cols: vec![meta.advice_column()],
+            next: 0,
+            counting: true,
+            usage: ColumnUsage {
+                available,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn alloc(&mut self, table: &'static str) -> Column<Advice> {
+        let col = if self.counting {
+            self.cols[0]
+        } else {
+            *self
+                .cols
+                .get(self.next)
+                .expect("columns are counted before they are allocated")
+        };
+
+        self.next += 1;
+        self.usage.required = self.usage.required.max(self.next);
+        *self.usage.tables.entry(table).or_default() += 1;
+
+        col
+    }
+
+    /// Columns of `table`, taken from the allocator as they are consumed.
+    pub fn table(&mut self, table: &'static str) -> TableColumns<'_> {
+        TableColumns {
+            allocator: self,
+            table,
+        }
+    }
+
+    /// Starts a group of tables sharing their columns.
+    pub fn share(&self) -> SharedColumns {
+        SharedColumns {
+            start: self.next,
+            end: self.next,
+        }
+    }
+
+    /// Columns of `table` in the group, starting from the first column of the group.
+    pub fn shared(&mut self, shared: &mut SharedColumns, table: &'static str) -> TableColumns<'_> {
+        shared.end = shared.end.max(self.next);
+        self.next = shared.start;
+        self.table(table)
+    }
+
+    /// Ends the group, the following tables take the columns after its widest table.
+    pub fn end_share(&mut self, shared: SharedColumns) {
+        self.next = self.next.max(shared.end);
+    }
+
+    pub fn usage(&self) -> ColumnUsage {
+        self.usage.clone()
+    }
+}
+
+pub struct TableColumns<'a> {
+    allocator: &'a mut ColumnAllocator,
+    table: &'static str,
+}
+
+impl Iterator for TableColumns<'_> {
+    type Item = Column<Advice>;
+
+    fn next(&mut self) -> Option<Column<Advice>> {
+        Some(self.allocator.alloc(self.table))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::pairing::bn256::Fr;
+    use halo2_proofs::plonk::{Advice, Column, ConstraintSystem};
+
+    use crate::circuits::utils::allocator::{ColumnAllocator, ColumnExhausted};
+
+    fn configure(cols: &mut ColumnAllocator, tables: usize) -> Vec<Column<Advice>> {
+        let mut allocated = cols.table("a").take(2).collect::<Vec<_>>();
+        let mut shared = cols.share();
+        allocated.extend(cols.shared(&mut shared, "b").take(3));
+        cols.shared(&mut shared, "c").next().unwrap();
+        cols.end_share(shared);
+        for _ in 0..tables {
+            allocated.extend(cols.table("d").next());
+        }
+        allocated
+    }
+
+    #[test]
+    fn test_column_allocator() {
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let mut cols = ColumnAllocator::counting(&mut meta, 6);
+        configure(&mut cols, 1);
+        let usage = cols.usage();
+        assert_eq!(usage.required, 6);
+        assert_eq!(usage.tables["b"], 3);
+        assert_eq!(usage.tables["c"], 1);
+        assert!(usage.check().is_ok());
+
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let mut cols = ColumnAllocator::new(&mut meta, 6, usage.required).unwrap();
+        let allocated = configure(&mut cols, 1);
+        assert_eq!(allocated.len(), 6);
+        for (i, col) in allocated.iter().enumerate() {
+            assert!(!allocated[..i].contains(col));
+        }
+        assert_eq!(cols.usage(), usage);
+
+        // the columns over the budget are counted, but not allocated
+        let mut cols = ColumnAllocator::counting(&mut meta, 6);
+        configure(&mut cols, 2);
+        let usage = cols.usage();
+        assert_eq!(
+            usage.check(),
+            Err(ColumnExhausted {
+                required: 7,
+                available: 6
+            })
+        );
+        assert_eq!(
+            ColumnAllocator::new(&mut meta, 6, usage.required).err(),
+            Some(ColumnExhausted {
+                required: 7,
+                available: 6
+            })
+        );
+    }
+}
@@ -3,6 +3,8 @@ use std::io::Read;
 use halo2_proofs::{arithmetic::FieldExt, circuit::Region};
 use num_bigint::BigUint;
 
+pub mod allocator;
+pub mod poseidon;
 pub mod row_diff;
 pub mod tvalue;
 pub mod value_64;
@@ -0,0 +1,426 @@
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+
+use halo2_proofs::arithmetic::FieldExt;
+use halo2_proofs::circuit::AssignedCell;
+use halo2_proofs::plonk::{
+    Advice, Column, ConstraintSystem, Error, Expression, Fixed, Selector, VirtualCells,
+};
+use halo2_proofs::poly::Rotation;
+use num_bigint::BigUint;
+use num_traits::Zero;
+
+use crate::circuits::utils::{bn_to_field, Context};
+use crate::{constant, cur, next};
+
+pub const T: usize = 5;
+pub const RATE: usize = T - 1;
+pub const R_F: usize = 8;
+pub const R_P: usize = 60;
+pub const ROUNDS: usize = R_F + R_P;
+
+/// Grain LFSR used by the Poseidon reference implementation to derive round constants.
+struct Grain {
+    state: VecDeque<bool>,
+    field_size: usize,
+}
+
+impl Grain {
+    fn new(field_size: usize) -> Grain {
+        let mut state = VecDeque::new();
+        let mut push = |value: usize, width: usize| {
+            for i in (0..width).rev() {
+                state.push_back((value >> i) & 1 == 1);
+            }
+        };
+
+        // field: prime field, sbox: x^alpha
+        push(1, 2);
+        push(0, 4);
+        push(field_size, 12);
+        push(T, 12);
+        push(R_F, 10);
+        push(R_P, 10);
+        push((1 << 30) - 1, 30);
+
+        let mut grain = Grain { state, field_size };
+        for _ in 0..160 {
+            grain.next_bit();
+        }
+
+        grain
+    }
+
+    fn next_bit(&mut self) -> bool {
+        let s = &self.state;
+        let bit = s[62] ^ s[51] ^ s[38] ^ s[23] ^ s[13] ^ s[0];
+        self.state.pop_front();
+        self.state.push_back(bit);
+        bit
+    }
+
+    fn next_filtered_bit(&mut self) -> bool {
+        loop {
+            let keep = self.next_bit();
+            let bit = self.next_bit();
+            if keep {
+                return bit;
+            }
+        }
+    }
+
+    fn next_bits(&mut self) -> BigUint {
+        let mut bn = BigUint::zero();
+        for _ in 0..self.field_size {
+            bn <<= 1;
+            if self.next_filtered_bit() {
+                bn += 1u64;
+            }
+        }
+
+        bn
+    }
+
+    fn next_field_element<F: FieldExt>(&mut self, modulus: &BigUint) -> F {
+        loop {
+            let bn = self.next_bits();
+            if &bn < modulus {
+                return bn_to_field(&bn);
+            }
+        }
+    }
+
+    /// Like the reference, the MDS elements are reduced instead of rejected.
+    fn next_field_element_reduced<F: FieldExt>(&mut self) -> F {
+        bn_to_field(&self.next_bits())
+    }
+}
+
+/// Poseidon with x^5 s-box, width `T` and rate `RATE`.
+///
+/// Round constants and the MDS matrix follow the reference Grain LFSR
+/// generation, the matrix is the Cauchy matrix `1 / (x_i + y_j)` of the `2 * T`
+/// elements drawn after the constants.
+#[derive(Clone)]
+pub struct PoseidonSpec<F: FieldExt> {
+    pub round_constants: Vec<[F; T]>,
+    pub mds: [[F; T]; T],
+}
+
+impl<F: FieldExt> PoseidonSpec<F> {
+    pub fn new() -> PoseidonSpec<F> {
+        let modulus =
+            BigUint::parse_bytes(F::MODULUS.trim_start_matches("0x").as_bytes(), 16).unwrap();
+        let mut grain = Grain::new(F::NUM_BITS as usize);
+
+        let round_constants = (0..ROUNDS)
+            .map(|_| [(); T].map(|_| grain.next_field_element(&modulus)))
+            .collect();
+
+        let (xs, ys) = loop {
+            let elements = [(); 2 * T].map(|_| grain.next_field_element_reduced::<F>());
+            let (xs, ys) = elements.split_at(T);
+
+            let distinct = (0..2 * T).all(|i| !elements[..i].contains(&elements[i]));
+            if distinct
+                && xs
+                    .iter()
+                    .all(|x| ys.iter().all(|y| !bool::from((*x + y).is_zero())))
+            {
+                break (xs.to_vec(), ys.to_vec());
+            }
+        };
+
+        let mut mds = [[F::zero(); T]; T];
+        for i in 0..T {
+            for j in 0..T {
+                mds[i][j] = (xs[i] + ys[j]).invert().unwrap();
+            }
+        }
+
+        PoseidonSpec {
+            round_constants,
+            mds,
+        }
+    }
+
+    pub fn is_full_round(round: usize) -> bool {
+        round < R_F / 2 || round >= R_F / 2 + R_P
+    }
+
+    pub fn round(&self, round: usize, state: &[F; T]) -> [F; T] {
+        let mut sboxed = [F::zero(); T];
+        for i in 0..T {
+            sboxed[i] = state[i] + self.round_constants[round][i];
+            if i == 0 || Self::is_full_round(round) {
+                sboxed[i] = sboxed[i].square().square() * sboxed[i];
+            }
+        }
+
+        let mut next = [F::zero(); T];
+        for i in 0..T {
+            for j in 0..T {
+                next[i] = next[i] + self.mds[i][j] * sboxed[j];
+            }
+        }
+
+        next
+    }
+
+    pub fn permute(&self, state: [F; T]) -> [F; T] {
+        (0..ROUNDS).fold(state, |state, round| self.round(round, &state))
+    }
+
+    /// Sponge over `inputs`, the input length is put in the capacity element so
+    /// that zero padding of the last block stays injective.
+    pub fn hash(&self, inputs: &[F]) -> F {
+        let mut state = [F::zero(); T];
+        state[0] = F::from(inputs.len() as u64);
+
+        for block in inputs.chunks(RATE) {
+            for (i, input) in block.iter().enumerate() {
+                state[i + 1] = state[i + 1] + input;
+            }
+            state = self.permute(state);
+        }
+
+        state[1]
+    }
+}
+
+#[derive(Clone)]
+pub struct PoseidonConfig<F: FieldExt> {
+    pub state: [Column<Advice>; T],
+    pub inputs: [Column<Advice>; RATE],
+    round_constants: [Column<Fixed>; T],
+    s_init: Selector,
+    s_absorb: Selector,
+    s_full: Selector,
+    s_partial: Selector,
+    spec: PoseidonSpec<F>,
+    _mark: PhantomData<F>,
+}
+
+impl<F: FieldExt> PoseidonConfig<F> {
+    /// One row absorbs `RATE` inputs, then one row per round, the last state
+    /// row is the absorbing row of the next block.
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        cols: &mut impl Iterator<Item = Column<Advice>>,
+    ) -> PoseidonConfig<F> {
+        let state = [(); T].map(|_| cols.next().unwrap());
+        let inputs = [(); RATE].map(|_| cols.next().unwrap());
+        let round_constants = [(); T].map(|_| meta.fixed_column());
+        let s_init = meta.selector();
+        let s_absorb = meta.selector();
+        let s_full = meta.selector();
+        let s_partial = meta.selector();
+        let spec = PoseidonSpec::<F>::new();
+
+        meta.create_gate("poseidon init", |meta| {
+            let s = meta.query_selector(s_init);
+            let length = meta.query_fixed(round_constants[0], Rotation::cur());
+
+            let mut constraints = vec![s.clone() * (cur!(meta, state[0]) - length)];
+            for i in 1..T {
+                constraints.push(s.clone() * cur!(meta, state[i]));
+            }
+            constraints
+        });
+
+        meta.create_gate("poseidon absorb", |meta| {
+            let s = meta.query_selector(s_absorb);
+
+            let mut constraints = vec![s.clone() * (next!(meta, state[0]) - cur!(meta, state[0]))];
+            for i in 1..T {
+                constraints.push(
+                    s.clone()
+                        * (next!(meta, state[i])
+                            - cur!(meta, state[i])
+                            - cur!(meta, inputs[i - 1])),
+                );
+            }
+            constraints
+        });
+
+        let round_gate = |meta: &mut VirtualCells<'_, F>, full: bool| {
+            let sboxed = (0..T)
+                .map(|i| {
+                    let x = cur!(meta, state[i])
+                        + meta.query_fixed(round_constants[i], Rotation::cur());
+                    if i == 0 || full {
+                        x.clone() * x.clone() * x.clone() * x.clone() * x
+                    } else {
+                        x
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            (0..T)
+                .map(|i| {
+                    let mut acc = next!(meta, state[i]);
+                    for j in 0..T {
+                        acc = acc - constant!(spec.mds[i][j]) * sboxed[j].clone();
+                    }
+                    acc
+                })
+                .collect::<Vec<Expression<F>>>()
+        };
+
+        meta.create_gate("poseidon full round", |meta| {
+            let s = meta.query_selector(s_full);
+            round_gate(meta, true)
+                .into_iter()
+                .map(|x| s.clone() * x)
+                .collect::<Vec<_>>()
+        });
+
+        meta.create_gate("poseidon partial round", |meta| {
+            let s = meta.query_selector(s_partial);
+            round_gate(meta, false)
+                .into_iter()
+                .map(|x| s.clone() * x)
+                .collect::<Vec<_>>()
+        });
+
+        PoseidonConfig {
+            state,
+            inputs,
+            round_constants,
+            s_init,
+            s_absorb,
+            s_full,
+            s_partial,
+            spec,
+            _mark: PhantomData,
+        }
+    }
+
+    pub fn spec(&self) -> &PoseidonSpec<F> {
+        &self.spec
+    }
+
+    fn assign_state(
+        &self,
+        ctx: &mut Context<'_, F>,
+        state: &[F; T],
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        state
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                ctx.region
+                    .assign_advice(|| "poseidon state", self.state[i], ctx.offset, || Ok(*v))
+            })
+            .collect()
+    }
+
+    /// Assigns the sponge of `inputs` from the current offset, returns the input
+    /// cells and the cell holding the digest.
+    pub fn assign(
+        &self,
+        ctx: &mut Context<'_, F>,
+        inputs: &[F],
+    ) -> Result<(Vec<AssignedCell<F, F>>, AssignedCell<F, F>), Error> {
+        let mut state = [F::zero(); T];
+        state[0] = F::from(inputs.len() as u64);
+
+        self.s_init.enable(&mut ctx.region, ctx.offset)?;
+        ctx.region.assign_fixed(
+            || "poseidon length",
+            self.round_constants[0],
+            ctx.offset,
+            || Ok(state[0]),
+        )?;
+
+        let mut input_cells = vec![];
+
+        for block in inputs.chunks(RATE) {
+            self.s_absorb.enable(&mut ctx.region, ctx.offset)?;
+            self.assign_state(ctx, &state)?;
+            for i in 0..RATE {
+                let value = block.get(i).cloned().unwrap_or(F::zero());
+                let cell = ctx.region.assign_advice(
+                    || "poseidon input",
+                    self.inputs[i],
+                    ctx.offset,
+                    || Ok(value),
+                )?;
+                if i < block.len() {
+                    input_cells.push(cell);
+                }
+                state[i + 1] = state[i + 1] + value;
+            }
+            ctx.next();
+
+            for round in 0..ROUNDS {
+                if PoseidonSpec::<F>::is_full_round(round) {
+                    self.s_full.enable(&mut ctx.region, ctx.offset)?;
+                } else {
+                    self.s_partial.enable(&mut ctx.region, ctx.offset)?;
+                }
+                for i in 0..T {
+                    ctx.region.assign_fixed(
+                        || "poseidon round constant",
+                        self.round_constants[i],
+                        ctx.offset,
+                        || Ok(self.spec.round_constants[round][i]),
+                    )?;
+                }
+                self.assign_state(ctx, &state)?;
+                state = self.spec.round(round, &state);
+                ctx.next();
+            }
+        }
+
+        let mut state_cells = self.assign_state(ctx, &state)?;
+        ctx.next();
+
+        Ok((input_cells, state_cells.swap_remove(1)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::pairing::bn256::Fr as Fp;
+    use num_bigint::BigUint;
+
+    use crate::circuits::utils::bn_to_field;
+    use crate::circuits::utils::poseidon::PoseidonSpec;
+
+    fn fp(hex: &str) -> Fp {
+        bn_to_field(&BigUint::parse_bytes(hex.as_bytes(), 16).unwrap())
+    }
+
+    /// Output of the reference implementation for the x^5 permutation of width
+    /// 5 over bn254, `poseidonperm_x5_254_5.sage`.
+    #[test]
+    fn test_permutation_vector() {
+        let spec = PoseidonSpec::<Fp>::new();
+        let state = [0, 1, 2, 3, 4].map(Fp::from);
+
+        assert_eq!(
+            spec.permute(state),
+            [
+                "299c867db6c1fdd79dcefa40e4510b9837e60ebb1ce0663dbaa525df65250465",
+                "1148aaef609aa338b27dafd89bb98862d8bb2b429aceac47d86206154ffe053d",
+                "24febb87fed7462e23f6665ff9a0111f4044c38ee1672c1ac6b0637d34f24907",
+                "0eb08f6d809668a981c186beaf6110060707059576406b248e5d9cf6e78b3d3e",
+                "07748bc6877c9b82c8b98666ee9d0626ec7f5be4205f79ee8528ef1c4a376fc7",
+            ]
+            .map(fp)
+        );
+    }
+
+    #[test]
+    fn test_hash_padding() {
+        let spec = PoseidonSpec::<Fp>::new();
+
+        // the padded block is the same, the length is not
+        assert_ne!(spec.hash(&[]), spec.hash(&[Fp::from(0)]));
+        assert_ne!(
+            spec.hash(&[Fp::from(1)]),
+            spec.hash(&[Fp::from(1), Fp::from(0)])
+        );
+    }
+}
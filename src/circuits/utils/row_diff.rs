@@ -65,28 +65,46 @@ impl<F: FieldExt> RowDiffConfig<F> {
     }
 
     pub fn assign(&self, ctx: &mut Context<F>, data: F, diff: F) -> Result<(), Error> {
-        ctx.region
-            .assign_advice_from_constant(|| "row diff data", self.data, ctx.offset, data)?;
+        self.assign_witness(ctx, &RowDiffWitness::new(data, diff))
+    }
 
-        ctx.region.assign_advice(
-            || "row diff inv",
-            self.inv,
-            ctx.offset,
-            || Ok(diff.invert().unwrap_or(F::zero())),
-        )?;
-        ctx.region.assign_advice(
-            || "row diff same",
-            self.same,
-            ctx.offset,
-            || {
-                Ok(if diff.is_zero().into() {
-                    F::one()
-                } else {
-                    F::zero()
-                })
-            },
-        )?;
+    pub fn assign_witness(
+        &self,
+        ctx: &mut Context<F>,
+        witness: &RowDiffWitness<F>,
+    ) -> Result<(), Error> {
+        for (name, col, value) in [
+            ("row diff data", self.data, witness.data),
+            ("row diff inv", self.inv, witness.inv),
+            ("row diff same", self.same, witness.same),
+        ] {
+            ctx.region
+                .assign_advice(|| name, col, ctx.offset, || Ok(value))?;
+        }
 
         Ok(())
     }
 }
+
+/// Values of a row, computed apart from the region so that rows can be
+/// computed in parallel.
+#[derive(Clone, Copy)]
+pub struct RowDiffWitness<F: FieldExt> {
+    data: F,
+    inv: F,
+    same: F,
+}
+
+impl<F: FieldExt> RowDiffWitness<F> {
+    pub fn new(data: F, diff: F) -> RowDiffWitness<F> {
+        RowDiffWitness {
+            data,
+            inv: diff.invert().unwrap_or(F::zero()),
+            same: if diff.is_zero().into() {
+                F::one()
+            } else {
+                F::zero()
+            },
+        }
+    }
+}
@@ -0,0 +1,436 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::marker::PhantomData;
+
+use halo2_proofs::plonk::{ConstraintSystem, Error};
+use halo2_proofs::{arithmetic::FieldExt, circuit::SimpleFloorPlanner, plonk::Circuit};
+use specs::segment::{MachineState, Segment};
+use specs::{CompileTable, ExecutionTable};
+
+use crate::circuits::event::{CircuitOptions, EventChip, EventConfig};
+use crate::circuits::image::{image_rows, ImageChip, ImageConfig, IMAGE_COLUMNS};
+use crate::circuits::instruction::{InstructionChip, InstructionConfig, INSTRUCTION_COLUMNS};
+use crate::circuits::io::IOConfig;
+use crate::circuits::jump::{JumpChip, JumpConfig};
+use crate::circuits::memory::{MemoryChip, MemoryConfig};
+use crate::circuits::memory_init::{InitMemoryConfig, MemoryInitChip};
+use crate::circuits::range::{RangeChip, RangeConfig};
+use crate::circuits::state::StateConfig;
+use crate::circuits::utils::allocator::{ColumnAllocator, ColumnExhausted, ColumnUsage};
+use crate::circuits::utils::Context;
+
+const VAR_COLUMNS: usize = 64;
+const COMMON_RANGE: usize = 16;
+
+/// Two-adicity of the bn256 scalar field, no evaluation domain can be larger.
+pub const MAX_K: u32 = 28;
+
+/// Options of the circuit being configured, along with its advice columns
+/// when they are already counted.
+#[derive(Clone, Default)]
+struct Configuring {
+    options: CircuitOptions,
+    columns: Option<usize>,
+}
+
+thread_local! {
+    static CONFIGURING: RefCell<Configuring> = RefCell::new(Configuring::default());
+}
+
+fn configuring<R>(configuring: Configuring, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<Configuring>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take().unwrap();
+            CONFIGURING.with(|c| *c.borrow_mut() = previous);
+        }
+    }
+
+    let _restore = Restore(Some(CONFIGURING.with(|c| c.replace(configuring))));
+
+    f()
+}
+
+/// Runs `f` with `options` in place for `WasmCircuit::configure`.
+///
+/// `Circuit::configure` has no access to the circuit, so the options are only
+/// installed around the halo2 calls which configure it, and restored afterwards.
+/// Everything else passes the options explicitly.
+pub fn with_options<R>(options: &CircuitOptions, f: impl FnOnce() -> R) -> R {
+    configuring(
+        Configuring {
+            options: options.clone(),
+            columns: None,
+        },
+        f,
+    )
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CircuitSizeError {
+    /// The tables do not fit in the `2^k` rows.
+    Rows {
+        required_rows: usize,
+        max_rows: usize,
+    },
+    /// The tables take more advice columns than the budget of the circuit.
+    Columns(ColumnExhausted),
+}
+
+impl fmt::Display for CircuitSizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CircuitSizeError::Rows {
+                required_rows,
+                max_rows,
+            } => write!(
+                f,
+                "circuit requires {} rows, but at most {} rows are available with k = {}",
+                required_rows,
+                max_rows,
+                max_rows.trailing_zeros()
+            ),
+            CircuitSizeError::Columns(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct WasmCircuitConfig<F: FieldExt> {
+    range: RangeConfig<F>,
+    init_memory: InitMemoryConfig<F>,
+    instruction: InstructionConfig<F>,
+    io: IOConfig<F>,
+    image: ImageConfig<F>,
+    state: StateConfig<F>,
+    event: EventConfig<F>,
+    jump: JumpConfig<F>,
+    memory: MemoryConfig<F>,
+    columns: ColumnUsage,
+}
+
+#[derive(Default)]
+pub struct WasmCircuit<F: FieldExt> {
+    compile_tables: CompileTable,
+    execution_tables: ExecutionTable,
+    /// Registers after the last event, see `SegmentBoundary::end_state`.
+    end: Option<MachineState>,
+    /// Whether a segment follows.
+    has_end: bool,
+    options: CircuitOptions,
+    /// Counted once, they only depend on the options.
+    columns: ColumnUsage,
+    /// Rows the gates of the execution tables hold on, set by `sized`.
+    usable_rows: usize,
+    _data: PhantomData<F>,
+}
+
+impl<F: FieldExt> WasmCircuit<F> {
+    pub fn new(c: CompileTable, e: ExecutionTable, options: CircuitOptions) -> WasmCircuit<F> {
+        WasmCircuit {
+            compile_tables: c,
+            execution_tables: e,
+            end: None,
+            has_end: false,
+            columns: Self::count_columns(&options),
+            options,
+            usable_rows: 0,
+            _data: PhantomData,
+        }
+    }
+
+    pub fn from_segment(
+        c: CompileTable,
+        segment: Segment,
+        options: CircuitOptions,
+    ) -> WasmCircuit<F> {
+        WasmCircuit {
+            compile_tables: c,
+            execution_tables: segment.tables,
+            end: Some(segment.boundary.end_state()),
+            has_end: segment.boundary.end.is_some(),
+            columns: Self::count_columns(&options),
+            options,
+            usable_rows: 0,
+            _data: PhantomData,
+        }
+    }
+
+    pub fn options(&self) -> &CircuitOptions {
+        &self.options
+    }
+
+    /// Runs `f` with the options of the circuit in place, around every halo2
+    /// call which configures the circuit, see `with_options`.
+    pub fn configured<R>(&self, f: impl FnOnce() -> R) -> R {
+        configuring(
+            Configuring {
+                options: self.options.clone(),
+                columns: Some(self.columns.required),
+            },
+            f,
+        )
+    }
+
+    /// Rows taken by the longest table or region of the circuit.
+    pub fn rows(&self) -> usize {
+        let execution_rows = self
+            .execution_tables
+            .event
+            .len()
+            .max(self.execution_tables.memory.entries().len())
+            .max(self.execution_tables.jump.len())
+            // the last row queries its next row
+            + 1;
+        let io_rows = self.execution_tables.arguments().len()
+            + self.execution_tables.return_values().len()
+            + self.options.fuel_metering as usize;
+        // first fid, start and end registers, has end, sizes of the start and end
+        // stacks, then at most one row per stack location
+        let state_rows = 6 + self.execution_tables.memory.entries().len();
+
+        [
+            RangeChip::<F>::rows(COMMON_RANGE),
+            // tables start with a zero row
+            self.compile_tables.instructions.len() + 1,
+            self.compile_tables.init_memory.len() + 1,
+            image_rows::<F>(&self.compile_tables),
+            execution_rows,
+            io_rows,
+            state_rows,
+        ]
+        .into_iter()
+        .max()
+        .unwrap()
+    }
+
+    /// Rows reserved by halo2 for blinding, along with the last one.
+    fn reserved_rows(&self) -> usize {
+        let mut meta = ConstraintSystem::<F>::default();
+        Self::configure_for(&mut meta, &self.options, self.columns.required);
+
+        meta.blinding_factors() + 1
+    }
+
+    /// Rows of the circuit including the blinding rows reserved by halo2.
+    fn required_rows(&self) -> usize {
+        self.rows() + self.reserved_rows()
+    }
+
+    /// Checks that the circuit fits in `2^k` rows and in its column budget.
+    pub fn check_k(&self, k: u32) -> Result<(), CircuitSizeError> {
+        self.column_usage()
+            .check()
+            .map_err(CircuitSizeError::Columns)?;
+
+        let required_rows = self.required_rows();
+        let max_rows = 1 << k;
+        if required_rows > max_rows {
+            return Err(CircuitSizeError::Rows {
+                required_rows,
+                max_rows,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Sizes the circuit for `2^k` rows, keys and proofs must be created with
+    /// circuits of the same size.
+    pub fn sized(mut self, k: u32) -> Result<Self, CircuitSizeError> {
+        self.check_k(k)?;
+        self.usable_rows = (1 << k) - self.reserved_rows();
+
+        Ok(self)
+    }
+
+    /// Smallest `k` such that the circuit fits in `2^k` rows.
+    pub fn min_k(&self) -> Result<u32, CircuitSizeError> {
+        self.column_usage()
+            .check()
+            .map_err(CircuitSizeError::Columns)?;
+
+        let k = self.required_rows().next_power_of_two().trailing_zeros();
+        self.check_k(k.min(MAX_K))?;
+
+        Ok(k)
+    }
+
+    /// Advice columns taken by the circuit with its options.
+    pub fn column_usage(&self) -> ColumnUsage {
+        self.columns.clone()
+    }
+}
+
+impl<F: FieldExt> Circuit<F> for WasmCircuit<F> {
+    type Config = WasmCircuitConfig<F>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        // the compile tables and the options are fixed data of the circuit
+        WasmCircuit {
+            compile_tables: self.compile_tables.clone(),
+            execution_tables: ExecutionTable::default(),
+            end: None,
+            has_end: false,
+            options: self.options.clone(),
+            columns: self.columns.clone(),
+            usable_rows: self.usable_rows,
+            _data: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut halo2_proofs::plonk::ConstraintSystem<F>) -> Self::Config {
+        let Configuring { options, columns } = CONFIGURING.with(|c| c.borrow().clone());
+        let columns = columns.unwrap_or_else(|| Self::count_columns(&options).required);
+
+        Self::configure_for(meta, &options, columns)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl halo2_proofs::circuit::Layouter<F>,
+    ) -> Result<(), Error> {
+        if self.usable_rows < self.rows() {
+            return Err(Error::Synthesis);
+        }
+
+        let event = EventChip::new(config.event);
+        let range = RangeChip::new(config.range);
+        let instruction = InstructionChip::new(config.instruction);
+        let init_memory = MemoryInitChip::new(config.init_memory);
+        let image = ImageChip::new(config.image.clone());
+        let memory = MemoryChip::new(config.memory);
+        let jump = JumpChip::new(config.jump);
+
+        range.init(&mut layouter, COMMON_RANGE)?;
+        instruction.assign(&mut layouter, &self.compile_tables.instructions)?;
+        init_memory.add_memory_init(&mut layouter, &self.compile_tables.init_memory)?;
+        let (first_fid, image_id) = image.assign(&mut layouter, &self.compile_tables)?;
+
+        // the tables use disjoint columns, so every region starts at the first row
+        let event_cells = layouter.assign_region(
+            || "event table",
+            |region| {
+                let mut ctx = Context::new(region);
+                // the last usable row only serves as the next row of the one before
+                config.state.assign_rows(&mut ctx, self.usable_rows - 1)?;
+                event.assign(
+                    &mut ctx,
+                    &self.execution_tables.event,
+                    self.end,
+                    self.has_end,
+                )
+            },
+        )?;
+
+        let memory_cells = layouter.assign_region(
+            || "memory table",
+            |region| {
+                let mut ctx = Context::new(region);
+                memory.assign(
+                    &mut ctx,
+                    &self.execution_tables.memory.entries(),
+                    &event_cells.rest_mops,
+                    self.has_end,
+                )
+            },
+        )?;
+
+        layouter.assign_region(
+            || "jump table",
+            |region| {
+                let mut ctx = Context::new(region);
+                jump.assign(&mut ctx, &self.execution_tables.jump)
+            },
+        )?;
+
+        layouter.constrain_instance(image_id.cell(), config.image.instance(), 0)?;
+        layouter.constrain_instance(first_fid.cell(), config.state.instance(), 0)?;
+        layouter.constrain_instance(event_cells.has_end.cell(), config.state.instance(), 3)?;
+        layouter.constrain_instance(memory_cells.has_end.cell(), config.state.instance(), 3)?;
+        layouter.constrain_instance(memory_cells.inits.cell(), config.state.instance(), 4)?;
+        layouter.constrain_instance(memory_cells.ends.cell(), config.state.instance(), 5)?;
+
+        Ok(())
+    }
+}
+
+impl<F: FieldExt> WasmCircuit<F> {
+    /// Configures the tables on a scratch constraint system to count their columns.
+    fn count_columns(options: &CircuitOptions) -> ColumnUsage {
+        let mut scratch = ConstraintSystem::default();
+        let cols = ColumnAllocator::counting(&mut scratch, VAR_COLUMNS);
+
+        Self::configure_with(&mut scratch, cols, options).columns
+    }
+
+    /// Configures the tables with the `columns` counted by `count_columns`.
+    fn configure_for(
+        meta: &mut ConstraintSystem<F>,
+        options: &CircuitOptions,
+        columns: usize,
+    ) -> WasmCircuitConfig<F> {
+        // `configure` cannot fail, circuits over the budget are rejected by `check_k`
+        let cols = ColumnAllocator::new(meta, VAR_COLUMNS, columns)
+            .expect("circuits are sized before they are configured");
+
+        Self::configure_with(meta, cols, options)
+    }
+
+    fn configure_with(
+        meta: &mut ConstraintSystem<F>,
+        mut cols: ColumnAllocator,
+        options: &CircuitOptions,
+    ) -> WasmCircuitConfig<F> {
+        let range = RangeConfig::configure([0; 3].map(|_| meta.lookup_table_column()));
+
+        let init_memory = InitMemoryConfig::configure(meta.lookup_table_column());
+        let instruction = InstructionConfig::configure(
+            [0; INSTRUCTION_COLUMNS].map(|_| meta.lookup_table_column()),
+        );
+        let io = IOConfig::configure(meta.instance_column());
+        let state_col = meta.instance_column();
+        let state = StateConfig::configure(meta, state_col);
+        let mut image_cols = [(); IMAGE_COLUMNS]
+            .map(|_| meta.advice_column())
+            .into_iter();
+        let image = ImageConfig::configure(meta, &mut image_cols);
+        let jump = JumpConfig::configure(&mut cols.table("jump"));
+        let memory = MemoryConfig::configure(
+            meta,
+            &mut cols.table("memory"),
+            &range,
+            &init_memory,
+            &io,
+            &state,
+        );
+        let event = EventConfig::configure(
+            meta,
+            &mut cols,
+            &range,
+            &instruction,
+            &memory,
+            &jump,
+            &io,
+            &state,
+            options,
+        );
+
+        WasmCircuitConfig {
+            range,
+            init_memory,
+            event,
+            instruction,
+            io,
+            image,
+            state,
+            jump,
+            memory,
+            columns: cols.usage(),
+        }
+    }
+}
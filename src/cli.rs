@@ -0,0 +1,422 @@
+use std::fs;
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand};
+use halo2_proofs::pairing::bn256::Fr;
+use specs::checker::check_execution;
+use specs::types::Value;
+use specs::{CompileTable, ExecutionTable};
+
+use zkswavm::circuits::event::CircuitOptions;
+use zkswavm::prover::artifact::{
+    load_params, load_proof, load_proving_key, load_trace, load_verifying_key, save_params,
+    save_proof, save_proving_key, save_trace, save_verifying_key,
+};
+use zkswavm::prover::{image_public_inputs, prove, setup, setup_params, verify, ProverError};
+use zkswavm::runtime::{CompileOutcome, WasmInterpreter, WasmRuntime};
+use zkswavm::WasmCircuit;
+
+#[derive(Parser)]
+#[command(about = "Compile, run and prove wasm programs")]
+pub struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Executes the function, prints its results and the table sizes.
+    DryRun {
+        #[command(flatten)]
+        module: ModuleArgs,
+        #[command(flatten)]
+        args: ArgumentArgs,
+    },
+    /// Executes the function and saves its tables for `prove --trace`.
+    Trace {
+        #[command(flatten)]
+        module: ModuleArgs,
+        #[command(flatten)]
+        args: ArgumentArgs,
+        #[arg(long)]
+        output: PathBuf,
+        /// Writes the tables as JSON instead, which `prove --trace` reads as well.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Generates the keys of the program for the params of a trusted setup.
+    Setup {
+        #[command(flatten)]
+        module: ModuleArgs,
+        /// Arguments of an execution the circuit must fit, when the params are
+        /// generated and `k` is not given.
+        #[command(flatten)]
+        args: ArgumentArgs,
+        /// The circuit has `2^k` rows, by default the fewest fitting the execution.
+        #[arg(long)]
+        k: Option<u32>,
+        /// Params of the trusted setup, written there instead with `--insecure-dev-setup`.
+        #[arg(long)]
+        params: PathBuf,
+        /// Generates the params locally. Whoever ran it knows the toxic waste and
+        /// can forge proofs, so the params are for development only.
+        #[arg(long)]
+        insecure_dev_setup: bool,
+        #[arg(long)]
+        pk: PathBuf,
+        #[arg(long)]
+        vk: PathBuf,
+    },
+    /// Proves an execution, run here or loaded from a trace.
+    Prove {
+        #[command(flatten)]
+        module: ModuleArgs,
+        #[command(flatten)]
+        args: ArgumentArgs,
+        #[arg(long)]
+        trace: Option<PathBuf>,
+        #[arg(long)]
+        params: PathBuf,
+        #[arg(long)]
+        pk: PathBuf,
+        #[arg(long)]
+        output: PathBuf,
+    },
+    /// Verifies a proof of the program run with the arguments and returning the
+    /// values, the program is the one the verifying key was generated for.
+    Verify {
+        #[command(flatten)]
+        args: ArgumentArgs,
+        /// Claimed result of the function, as `<type>:<value>` like the arguments.
+        #[arg(long = "return", value_parser = parse_value)]
+        returns: Vec<Value>,
+        /// Claimed fuel consumption, for keys generated with fuel metering.
+        #[arg(long)]
+        fuel: Option<u64>,
+        #[arg(long)]
+        params: PathBuf,
+        #[arg(long)]
+        vk: PathBuf,
+        #[arg(long)]
+        proof: PathBuf,
+    },
+}
+
+#[derive(Args)]
+struct ModuleArgs {
+    /// Module in the text or the binary format.
+    #[arg(long)]
+    wasm: PathBuf,
+    /// Name of the exported function.
+    #[arg(long)]
+    function: String,
+}
+
+#[derive(Args)]
+struct ArgumentArgs {
+    /// Argument of the function as `<type>:<value>`, e.g. `i32:-1` or `u64:7`.
+    #[arg(long = "arg", value_parser = parse_value)]
+    args: Vec<Value>,
+}
+
+fn parse_value(arg: &str) -> Result<Value, String> {
+    let (vtype, value) = arg
+        .split_once(':')
+        .ok_or_else(|| format!("expected <type>:<value>, found {}", arg))?;
+    let invalid = |err: std::num::ParseIntError| format!("invalid {} {}: {}", vtype, value, err);
+
+    match vtype {
+        "i32" => value.parse().map(Value::I32).map_err(invalid),
+        "i64" => value.parse().map(Value::I64).map_err(invalid),
+        "u32" => value.parse().map(Value::U32).map_err(invalid),
+        "u64" => value.parse().map(Value::U64).map_err(invalid),
+        _ => Err(format!(
+            "unknown type {}, expected i32, i64, u32 or u64",
+            vtype
+        )),
+    }
+}
+
+/// Values as `<type>:<value>`, the way `--arg` and `--return` take them.
+fn format_values(values: &[Value]) -> String {
+    values
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn compile(
+    runtime: &WasmInterpreter,
+    module: &ModuleArgs,
+) -> Result<CompileOutcome<wasmi::Module>, String> {
+    let bytes = fs::read(&module.wasm).map_err(|err| err.to_string())?;
+
+    // binary modules start with the `\0asm` magic
+    let outcome = if bytes.starts_with(b"\0asm") {
+        runtime.compile_binary(&bytes, &module.function)
+    } else {
+        let text = String::from_utf8(bytes).map_err(|err| err.to_string())?;
+        runtime.compile(&text, &module.function)
+    };
+
+    outcome.map_err(|err| err.to_string())
+}
+
+fn execute(
+    runtime: &WasmInterpreter,
+    compiled: &CompileOutcome<wasmi::Module>,
+    args: &ArgumentArgs,
+) -> Result<(Vec<Value>, ExecutionTable), String> {
+    let outcome = runtime
+        .run(compiled, args.args.clone())
+        .map_err(|err| err.to_string())?;
+    check_execution(&compiled.tables, &outcome.tables).map_err(|err| err.to_string())?;
+
+    Ok((outcome.returns, outcome.tables))
+}
+
+fn print_tables(compile_table: &CompileTable, execution_table: &ExecutionTable) {
+    println!("instruction table: {}", compile_table.instructions.len());
+    println!("event table: {}", execution_table.event.len());
+    println!("memory table: {}", execution_table.memory.entries().len());
+    println!("jump table: {}", execution_table.jump.len());
+    println!("fuel: {}", execution_table.fuel());
+}
+
+pub fn run(cli: Cli) -> Result<(), String> {
+    let runtime = WasmInterpreter::new();
+
+    match cli.command {
+        Command::DryRun { module, args } => {
+            let compiled = compile(&runtime, &module)?;
+            let (returns, execution_table) = execute(&runtime, &compiled, &args)?;
+
+            println!("returns: {}", format_values(&returns));
+            print_tables(&compiled.tables, &execution_table);
+        }
+        Command::Trace {
+            module,
+            args,
+            output,
+            json,
+        } => {
+            let compiled = compile(&runtime, &module)?;
+            let (_, execution_table) = execute(&runtime, &compiled, &args)?;
+
+            if json {
+                let json =
+                    specs::codec::to_json(&execution_table).map_err(|err| err.to_string())?;
+                fs::write(&output, json).map_err(|err| err.to_string())?;
+            } else {
+                save_trace(&output, &compiled.tables, &execution_table)
+                    .map_err(|err| err.to_string())?;
+            }
+            print_tables(&compiled.tables, &execution_table);
+        }
+        Command::Setup {
+            module,
+            args,
+            k,
+            params,
+            insecure_dev_setup,
+            pk,
+            vk,
+        } => {
+            let compiled = compile(&runtime, &module)?;
+            let options = CircuitOptions::of(&compiled.tables);
+            let setup_params = if insecure_dev_setup {
+                let k = match k {
+                    Some(k) => k,
+                    None => {
+                        let (_, execution_table) = execute(&runtime, &compiled, &args)?;
+                        WasmCircuit::<Fr>::new(
+                            compiled.tables.clone(),
+                            execution_table,
+                            options.clone(),
+                        )
+                        .min_k()
+                        .map_err(|err| err.to_string())?
+                    }
+                };
+                let setup_params = setup_params(k);
+                save_params(&params, &setup_params).map_err(|err| err.to_string())?;
+                setup_params
+            } else {
+                let setup_params = load_params(&params).map_err(|err| err.to_string())?;
+                if let Some(k) = k.filter(|k| *k != setup_params.k()) {
+                    return Err(format!(
+                        "--k {} does not match the params, generated for k = {}",
+                        k,
+                        setup_params.k()
+                    ));
+                }
+                setup_params
+            };
+            println!("k: {}", setup_params.k());
+            let proving_key =
+                setup(&setup_params, &options, &compiled.tables).map_err(|err| err.to_string())?;
+
+            save_proving_key(&pk, &proving_key, &options, &compiled.tables)
+                .map_err(|err| err.to_string())?;
+            save_verifying_key(&vk, proving_key.get_vk(), &options, &compiled.tables)
+                .map_err(|err| err.to_string())?;
+        }
+        Command::Prove {
+            module,
+            args,
+            trace,
+            params,
+            pk,
+            output,
+        } => {
+            let compiled = compile(&runtime, &module)?;
+            let execution_table = match trace {
+                Some(trace) => {
+                    load_trace(&trace, &compiled.tables).map_err(|err| err.to_string())?
+                }
+                None => execute(&runtime, &compiled, &args)?.1,
+            };
+
+            let params = load_params(&params).map_err(|err| err.to_string())?;
+            let (pk, options) =
+                load_proving_key(&pk, &params, &compiled.tables).map_err(|err| err.to_string())?;
+            println!(
+                "returns: {}",
+                format_values(&execution_table.return_values())
+            );
+            if options.fuel_metering {
+                println!("fuel: {}", execution_table.fuel());
+            }
+            let proof = prove(&params, &pk, &options, compiled.tables, execution_table)
+                .map_err(|err| err.to_string())?;
+
+            save_proof(&output, &proof).map_err(|err| err.to_string())?;
+        }
+        Command::Verify {
+            args,
+            returns,
+            fuel,
+            params,
+            vk,
+            proof,
+        } => {
+            let params = load_params(&params).map_err(|err| err.to_string())?;
+            let (vk, options, image) =
+                load_verifying_key(&vk, &params).map_err(|err| err.to_string())?;
+            let proof = load_proof(&proof).map_err(|err| err.to_string())?;
+
+            if options.fuel_metering != fuel.is_some() {
+                return Err("--fuel must be given exactly for keys metering fuel".to_string());
+            }
+            // every instance is derived from the image of the key and the claimed io
+            let expected = image_public_inputs(&image, &args.args, &returns, fuel);
+            if proof.instances != expected {
+                return Err(
+                    "the proof is not about this program, arguments and returns".to_string()
+                );
+            }
+            verify(&params, &vk, &proof).map_err(|err| ProverError::from(err).to_string())?;
+
+            println!("image id: {:?}", image.id);
+            println!(
+                "module digest: {}",
+                image
+                    .module_digest
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect::<String>()
+            );
+            println!("arguments: {}", format_values(&args.args));
+            println!("returns: {}", format_values(&returns));
+            if let Some(fuel) = fuel {
+                println!("fuel: {}", fuel);
+            }
+            println!("proof verified");
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use clap::Parser;
+    use specs::types::Value;
+
+    use crate::cli::{parse_value, run, Cli};
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("zkswavm-cli-{}-{}", std::process::id(), name))
+    }
+
+    /// Runs the command line `args`, whose words are separated by spaces.
+    fn run_args(args: String) -> Result<(), String> {
+        run(Cli::parse_from(
+            ["zkswavm"].into_iter().chain(args.split_whitespace()),
+        ))
+    }
+
+    #[test]
+    fn test_parse_value() {
+        assert_eq!(parse_value("i32:-1"), Ok(Value::I32(-1)));
+        assert_eq!(parse_value("u64:7"), Ok(Value::U64(7)));
+        assert!(parse_value("u32:-1").is_err());
+        assert!(parse_value("f32:1").is_err());
+        assert!(parse_value("1").is_err());
+
+        // values are printed the way they are parsed
+        for value in [
+            Value::I32(-1),
+            Value::I64(i64::MIN),
+            Value::U32(7),
+            Value::U64(u64::MAX),
+        ] {
+            assert_eq!(parse_value(&value.to_string()), Ok(value));
+        }
+    }
+
+    #[test]
+    fn test_prove_and_verify() {
+        let textual_repr = r#"
+                (module
+                    (func (export "test") (param i32) (result i32)
+                      (local.get 0)
+                    )
+                   )
+                "#;
+        let paths = ["test.wat", "trace.json", "params", "pk", "vk", "proof"].map(temp_path);
+        let [wasm, trace, params, pk, vk, proof] = paths.each_ref().map(|path| path.display());
+        fs::write(&paths[0], textual_repr).unwrap();
+        let module = format!("--wasm {} --function test --arg i32:-3", wasm);
+        let keys = format!("--params {} --pk {} --vk {}", params, pk, vk);
+
+        // params are only generated when asked for
+        assert!(run_args(format!("setup {} {}", module, keys)).is_err());
+        run_args(format!("setup {} {} --insecure-dev-setup", module, keys)).unwrap();
+
+        run_args(format!("trace {} --output {} --json", module, trace)).unwrap();
+        run_args(format!(
+            "prove {} --trace {} --params {} --pk {} --output {}",
+            module, trace, params, pk, proof
+        ))
+        .unwrap();
+
+        let verify = |returned: &str| {
+            run_args(format!(
+                "verify --arg i32:-3 --return {} --params {} --vk {} --proof {}",
+                returned, params, vk, proof
+            ))
+        };
+        verify("i32:-3").unwrap();
+        assert!(verify("i32:3").is_err());
+        assert!(verify("i64:-3").is_err());
+
+        for path in paths {
+            fs::remove_file(path).unwrap();
+        }
+    }
+}
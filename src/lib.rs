@@ -0,0 +1,16 @@
+pub mod circuits;
+pub mod prover;
+pub mod runtime;
+
+#[cfg(test)]
+mod test;
+
+pub use specs;
+
+pub use circuits::wasm_circuit::WasmCircuit;
+pub use prover::{
+    image_public_inputs, prove, public_inputs, setup, setup_params, verify, ImageInfo, Proof,
+};
+pub use runtime::{
+    CompileOutcome, ExecutionLimits, ExecutionOutcome, WasmInterpreter, WasmRuntime,
+};
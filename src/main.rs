@@ -1,10 +1,10 @@
-extern crate core;
+use clap::Parser;
 
-pub mod circuits;
-pub mod runtime;
-
-pub mod test;
+mod cli;
 
 fn main() {
-    println!("Hello, world!");
+    if let Err(err) = cli::run(cli::Cli::parse()) {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
 }
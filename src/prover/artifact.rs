@@ -0,0 +1,549 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use halo2_proofs::arithmetic::BaseExt;
+use halo2_proofs::pairing::bn256::{Fr, G1Affine};
+use halo2_proofs::plonk::{ProvingKey, VerifyingKey};
+use halo2_proofs::poly::commitment::Params;
+use specs::itable::OpcodeClass;
+use specs::{CompileTable, ExecutionTable};
+use strum::IntoEnumIterator;
+
+use crate::circuits::event::CircuitOptions;
+use crate::circuits::image::image_id;
+use crate::circuits::instruction::Encode as _;
+use crate::circuits::wasm_circuit::{with_options, WasmCircuit};
+use crate::prover::{ImageInfo, Proof};
+
+const MAGIC: &[u8; 4] = b"ZKSW";
+pub const FORMAT_VERSION: u32 = 1;
+const CHECKSUM_SIZE: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArtifactKind {
+    Params = 1,
+    VerifyingKey = 2,
+    ProvingKey = 3,
+    Proof = 4,
+    Trace = 5,
+}
+
+#[derive(Debug)]
+pub enum ArtifactError {
+    Io(io::Error),
+    BadMagic,
+    UnsupportedVersion(u32),
+    KindMismatch {
+        expected: ArtifactKind,
+        found: u8,
+    },
+    ChecksumMismatch,
+    UnknownOpcodeClass(u8),
+    Decode(String),
+    /// The trace or the key was generated for another image.
+    ImageMismatch,
+    /// The event `eid` executes an instruction missing from the instruction table.
+    InstructionMismatch {
+        eid: u64,
+    },
+    /// A length field exceeds the bytes left in the artifact.
+    Truncated,
+}
+
+impl fmt::Display for ArtifactError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArtifactError::Io(e) => write!(f, "{}", e),
+            ArtifactError::BadMagic => write!(f, "not an artifact"),
+            ArtifactError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported artifact version {}, expected {}",
+                version, FORMAT_VERSION
+            ),
+            ArtifactError::KindMismatch { expected, found } => {
+                write!(
+                    f,
+                    "expected a {:?} artifact, found kind {}",
+                    expected, found
+                )
+            }
+            ArtifactError::ChecksumMismatch => write!(f, "artifact checksum mismatch"),
+            ArtifactError::UnknownOpcodeClass(class) => {
+                write!(f, "unknown opcode class {}", class)
+            }
+            ArtifactError::Decode(message) => write!(f, "failed to decode artifact: {}", message),
+            ArtifactError::ImageMismatch => write!(f, "artifact was generated for another image"),
+            ArtifactError::InstructionMismatch { eid } => write!(
+                f,
+                "event {} executes an instruction missing from the image",
+                eid
+            ),
+            ArtifactError::Truncated => write!(f, "artifact is truncated"),
+        }
+    }
+}
+
+impl std::error::Error for ArtifactError {}
+
+impl From<io::Error> for ArtifactError {
+    fn from(e: io::Error) -> Self {
+        ArtifactError::Io(e)
+    }
+}
+
+fn checksum(bytes: &[u8]) -> [u8; CHECKSUM_SIZE] {
+    let hash = blake2b_simd::Params::new()
+        .hash_length(CHECKSUM_SIZE)
+        .hash(bytes);
+    hash.as_bytes().try_into().unwrap()
+}
+
+/// Layout: magic | version u32 | kind u8 | payload length u64 | payload | blake2b(all previous bytes)
+fn write_artifact(path: &Path, kind: ArtifactKind, payload: &[u8]) -> Result<(), ArtifactError> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    bytes.push(kind as u8);
+    bytes.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(payload);
+    let checksum = checksum(&bytes);
+    bytes.extend_from_slice(&checksum);
+
+    File::create(path)?.write_all(&bytes)?;
+    Ok(())
+}
+
+fn read_artifact(path: &Path, kind: ArtifactKind) -> Result<Vec<u8>, ArtifactError> {
+    let mut bytes = vec![];
+    File::open(path)?.read_to_end(&mut bytes)?;
+
+    decode_artifact(&bytes, kind)
+}
+
+fn decode_artifact(bytes: &[u8], kind: ArtifactKind) -> Result<Vec<u8>, ArtifactError> {
+    let header_size = MAGIC.len() + 4 + 1 + 8;
+    if bytes.len() < header_size + CHECKSUM_SIZE || &bytes[..MAGIC.len()] != MAGIC {
+        return Err(ArtifactError::BadMagic);
+    }
+
+    let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+    if version != FORMAT_VERSION {
+        return Err(ArtifactError::UnsupportedVersion(version));
+    }
+
+    if bytes[8] != kind as u8 {
+        return Err(ArtifactError::KindMismatch {
+            expected: kind,
+            found: bytes[8],
+        });
+    }
+
+    let content_len = usize::try_from(u64::from_le_bytes(
+        bytes[9..header_size].try_into().unwrap(),
+    ))
+    .ok()
+    .and_then(|payload_len| payload_len.checked_add(header_size));
+    if content_len.and_then(|len| len.checked_add(CHECKSUM_SIZE)) != Some(bytes.len()) {
+        return Err(ArtifactError::ChecksumMismatch);
+    }
+
+    let (content, expected) = bytes.split_at(content_len.unwrap());
+    if checksum(content) != expected {
+        return Err(ArtifactError::ChecksumMismatch);
+    }
+
+    Ok(content[header_size..].to_vec())
+}
+
+pub fn save_params(path: &Path, params: &Params<G1Affine>) -> Result<(), ArtifactError> {
+    let mut payload = vec![];
+    params.write(&mut payload)?;
+    write_artifact(path, ArtifactKind::Params, &payload)
+}
+
+pub fn load_params(path: &Path) -> Result<Params<G1Affine>, ArtifactError> {
+    let payload = read_artifact(path, ArtifactKind::Params)?;
+    Ok(Params::<G1Affine>::read(&mut &payload[..])?)
+}
+
+/// Keys depend on the options they were generated with, so the options are
+/// stored in front of the key.
+fn write_circuit_options(payload: &mut Vec<u8>, options: &CircuitOptions) {
+    payload.push(options.opcode_classes.len() as u8);
+    payload.extend(options.opcode_classes.iter().map(|class| *class as u8));
+    payload.push(options.fuel_metering as u8);
+}
+
+fn read_circuit_options(reader: &mut &[u8]) -> Result<CircuitOptions, ArtifactError> {
+    let mut len = [0u8];
+    reader.read_exact(&mut len)?;
+    let mut classes = vec![0u8; len[0] as usize];
+    reader.read_exact(&mut classes)?;
+    let mut metering = [0u8];
+    reader.read_exact(&mut metering)?;
+
+    let opcode_classes = classes
+        .into_iter()
+        .map(|byte| {
+            OpcodeClass::iter()
+                .find(|class| *class as u8 == byte)
+                .ok_or(ArtifactError::UnknownOpcodeClass(byte))
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(CircuitOptions {
+        opcode_classes,
+        fuel_metering: metering[0] != 0,
+    })
+}
+
+fn write_image_info(payload: &mut Vec<u8>, image: &ImageInfo) -> io::Result<()> {
+    image.id.write(payload)?;
+    payload.extend_from_slice(&image.first_fid.to_le_bytes());
+    payload.extend_from_slice(&image.module_digest);
+    Ok(())
+}
+
+fn read_image_info(reader: &mut &[u8]) -> io::Result<ImageInfo> {
+    let id = Fr::read(reader)?;
+    let mut first_fid = [0u8; 2];
+    reader.read_exact(&mut first_fid)?;
+    let mut module_digest = [0u8; 32];
+    reader.read_exact(&mut module_digest)?;
+
+    Ok(ImageInfo {
+        id,
+        first_fid: u16::from_le_bytes(first_fid),
+        module_digest,
+    })
+}
+
+/// The image of `compile_table` is stored in front of the key, so that proofs
+/// can be verified without compiling the module again.
+pub fn save_verifying_key(
+    path: &Path,
+    vk: &VerifyingKey<G1Affine>,
+    options: &CircuitOptions,
+    compile_table: &CompileTable,
+) -> Result<(), ArtifactError> {
+    let mut payload = vec![];
+    write_image_info(&mut payload, &ImageInfo::of(compile_table))?;
+    write_circuit_options(&mut payload, options);
+    vk.write(&mut payload)?;
+    write_artifact(path, ArtifactKind::VerifyingKey, &payload)
+}
+
+/// The constraint system is rebuilt from the circuit type and the recorded
+/// circuit options, so no compile table is needed. The options and the image
+/// are returned along with the key.
+pub fn load_verifying_key(
+    path: &Path,
+    params: &Params<G1Affine>,
+) -> Result<(VerifyingKey<G1Affine>, CircuitOptions, ImageInfo), ArtifactError> {
+    let payload = read_artifact(path, ArtifactKind::VerifyingKey)?;
+    let mut reader = &payload[..];
+    let image = read_image_info(&mut reader)?;
+    let options = read_circuit_options(&mut reader)?;
+
+    let vk = with_options(&options, || {
+        VerifyingKey::<G1Affine>::read::<_, WasmCircuit<Fr>>(&mut reader, params)
+    })?;
+    Ok((vk, options, image))
+}
+
+/// The image id of `compile_table` is stored in front of the key, the key
+/// only proves executions of that image.
+pub fn save_proving_key(
+    path: &Path,
+    pk: &ProvingKey<G1Affine>,
+    options: &CircuitOptions,
+    compile_table: &CompileTable,
+) -> Result<(), ArtifactError> {
+    let mut payload = vec![];
+    image_id::<Fr>(compile_table).write(&mut payload)?;
+    write_circuit_options(&mut payload, options);
+    pk.write(&mut payload)?;
+    write_artifact(path, ArtifactKind::ProvingKey, &payload)
+}
+
+/// Loads a proving key saved by `save_proving_key` for the image compiled to
+/// `compile_table`, along with its options.
+pub fn load_proving_key(
+    path: &Path,
+    params: &Params<G1Affine>,
+    compile_table: &CompileTable,
+) -> Result<(ProvingKey<G1Affine>, CircuitOptions), ArtifactError> {
+    let payload = read_artifact(path, ArtifactKind::ProvingKey)?;
+    let mut reader = &payload[..];
+
+    if Fr::read(&mut reader)? != image_id(compile_table) {
+        return Err(ArtifactError::ImageMismatch);
+    }
+    let options = read_circuit_options(&mut reader)?;
+
+    let pk = with_options(&options, || {
+        ProvingKey::<G1Affine>::read::<_, WasmCircuit<Fr>>(&mut reader, params)
+    })?;
+    Ok((pk, options))
+}
+
+pub fn save_proof(path: &Path, proof: &Proof) -> Result<(), ArtifactError> {
+    let mut payload = vec![];
+    payload.write_all(&(proof.instances.len() as u32).to_le_bytes())?;
+    for column in proof.instances.iter() {
+        payload.write_all(&(column.len() as u32).to_le_bytes())?;
+        for value in column {
+            value.write(&mut payload)?;
+        }
+    }
+    payload.write_all(&(proof.transcript.len() as u64).to_le_bytes())?;
+    payload.write_all(&proof.transcript)?;
+
+    write_artifact(path, ArtifactKind::Proof, &payload)
+}
+
+pub fn load_proof(path: &Path) -> Result<Proof, ArtifactError> {
+    let payload = read_artifact(path, ArtifactKind::Proof)?;
+    let mut reader = &payload[..];
+
+    let mut u32_bytes = [0u8; 4];
+    let mut u64_bytes = [0u8; 8];
+
+    reader.read_exact(&mut u32_bytes)?;
+    let columns = u32::from_le_bytes(u32_bytes);
+    let mut instances = vec![];
+    for _ in 0..columns {
+        reader.read_exact(&mut u32_bytes)?;
+        let len = u32::from_le_bytes(u32_bytes);
+        instances.push(
+            (0..len)
+                .map(|_| Fr::read(&mut reader))
+                .collect::<io::Result<Vec<_>>>()?,
+        );
+    }
+
+    reader.read_exact(&mut u64_bytes)?;
+    let len = u64::from_le_bytes(u64_bytes);
+    if len > reader.len() as u64 {
+        return Err(ArtifactError::Truncated);
+    }
+    let mut transcript = vec![0u8; len as usize];
+    reader.read_exact(&mut transcript)?;
+
+    Ok(Proof {
+        instances,
+        transcript,
+    })
+}
+
+/// Saves the execution of the image compiled to `compile_table`, so that it can
+/// be proven elsewhere. The image id is stored in front of the tables.
+pub fn save_trace(
+    path: &Path,
+    compile_table: &CompileTable,
+    execution_table: &ExecutionTable,
+) -> Result<(), ArtifactError> {
+    let mut payload = vec![];
+    image_id::<Fr>(compile_table).write(&mut payload)?;
+    payload.extend(
+        specs::codec::to_bytes(execution_table)
+            .map_err(|err| ArtifactError::Decode(err.to_string()))?,
+    );
+    write_artifact(path, ArtifactKind::Trace, &payload)
+}
+
+/// Loads a trace saved by `save_trace`, or the JSON tables of `specs::codec::to_json`,
+/// for the image compiled to `compile_table`. Every executed instruction must be
+/// in its instruction table.
+pub fn load_trace(
+    path: &Path,
+    compile_table: &CompileTable,
+) -> Result<ExecutionTable, ArtifactError> {
+    let mut bytes = vec![];
+    File::open(path)?.read_to_end(&mut bytes)?;
+
+    let execution_table: ExecutionTable = if bytes.starts_with(MAGIC) {
+        let payload = decode_artifact(&bytes, ArtifactKind::Trace)?;
+        let mut reader = &payload[..];
+
+        if Fr::read(&mut reader)? != image_id(compile_table) {
+            return Err(ArtifactError::ImageMismatch);
+        }
+        specs::codec::from_bytes(reader).map_err(|err| ArtifactError::Decode(err.to_string()))?
+    } else {
+        // JSON tables carry no image id, the instruction check below binds them
+        let json =
+            String::from_utf8(bytes).map_err(|err| ArtifactError::Decode(err.to_string()))?;
+        specs::codec::from_json(&json).map_err(|err| ArtifactError::Decode(err.to_string()))?
+    };
+
+    let instructions = compile_table
+        .instructions
+        .iter()
+        .map(|entry| entry.encode())
+        .collect::<HashSet<_>>();
+    let executed = execution_table
+        .event
+        .iter()
+        .map(|entry| (entry.eid, &entry.inst))
+        .chain(
+            execution_table
+                .jump
+                .iter()
+                .map(|entry| (entry.eid, entry.inst.as_ref())),
+        );
+    for (eid, inst) in executed {
+        if !instructions.contains(&inst.encode()) {
+            return Err(ArtifactError::InstructionMismatch { eid });
+        }
+    }
+
+    Ok(execution_table)
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::pairing::bn256::Fr;
+    use std::fs;
+
+    use crate::circuits::event::CircuitOptions;
+    use crate::prover::artifact::{
+        load_proof, load_trace, read_circuit_options, read_image_info, save_proof, save_trace,
+        write_artifact, write_circuit_options, write_image_info, ArtifactError, ArtifactKind,
+    };
+    use crate::prover::{ImageInfo, Proof};
+    use crate::runtime::{WasmInterpreter, WasmRuntime};
+    use crate::test::temp_path;
+    use crate::test::test_circuit_builder::run_test_circuit;
+    use specs::itable::Opcode;
+    use specs::types::Value;
+
+    #[test]
+    fn test_proof_round_trip() {
+        let path = temp_path("proof.bin");
+        let proof = Proof {
+            instances: vec![vec![Fr::from(1u64), Fr::from(2u64)], vec![Fr::from(3u64)]],
+            transcript: vec![4, 5, 6],
+        };
+
+        save_proof(&path, &proof).unwrap();
+        assert_eq!(load_proof(&path).unwrap(), proof);
+
+        // lengths are checked before anything is allocated
+        let mut payload = 0u32.to_le_bytes().to_vec();
+        payload.extend_from_slice(&u64::MAX.to_le_bytes());
+        write_artifact(&path, ArtifactKind::Proof, &payload).unwrap();
+        assert!(matches!(load_proof(&path), Err(ArtifactError::Truncated)));
+
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[9..17].copy_from_slice(&u64::MAX.to_le_bytes());
+        fs::write(&path, bytes).unwrap();
+        assert!(matches!(
+            load_proof(&path),
+            Err(ArtifactError::ChecksumMismatch)
+        ));
+
+        save_proof(&path, &proof).unwrap();
+
+        let mut bytes = fs::read(&path).unwrap();
+        let last = bytes.len() - 40;
+        bytes[last] ^= 1;
+        fs::write(&path, bytes).unwrap();
+        assert!(matches!(
+            load_proof(&path),
+            Err(ArtifactError::ChecksumMismatch)
+        ));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_circuit_options_round_trip() {
+        let options = CircuitOptions {
+            opcode_classes: CircuitOptions::default()
+                .opcode_classes
+                .into_iter()
+                .take(2)
+                .collect(),
+            fuel_metering: true,
+        };
+
+        let mut payload = vec![];
+        write_circuit_options(&mut payload, &options);
+        assert_eq!(read_circuit_options(&mut &payload[..]).unwrap(), options);
+    }
+
+    #[test]
+    fn test_image_info_round_trip() {
+        let compile_table = WasmInterpreter::new()
+            .compile(r#"(module (func (export "test")))"#, "test")
+            .unwrap()
+            .tables;
+        let image = ImageInfo::of(&compile_table);
+
+        let mut payload = vec![];
+        write_image_info(&mut payload, &image).unwrap();
+        assert_eq!(read_image_info(&mut &payload[..]).unwrap(), image);
+        assert!(read_image_info(&mut &payload[..payload.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_trace_round_trip() {
+        let textual_repr = r#"
+                (module
+                    (func (export "test") (param i32)
+                      (local.get 0)
+                      (drop)
+                    )
+                   )
+                "#;
+        let path = temp_path("trace.bin");
+
+        let compiler = WasmInterpreter::new();
+        let compiled_module = compiler.compile(textual_repr, "test").unwrap();
+        let execution_log = compiler.run(&compiled_module, vec![Value::I32(3)]).unwrap();
+        save_trace(&path, &compiled_module.tables, &execution_log.tables).unwrap();
+
+        // the proving side compiles the module again and only loads the trace
+        let compile_table = compiler.compile(textual_repr, "test").unwrap().tables;
+        let execution_table = load_trace(&path, &compile_table).unwrap();
+        assert_eq!(execution_table.arguments(), vec![3]);
+        run_test_circuit::<Fr>(compile_table.clone(), execution_table).unwrap();
+
+        let other = compiler
+            .compile(
+                r#"(module (func (export "test") (param i32) (local.get 0) (drop) (return)))"#,
+                "test",
+            )
+            .unwrap()
+            .tables;
+        assert!(matches!(
+            load_trace(&path, &other),
+            Err(ArtifactError::ImageMismatch)
+        ));
+
+        let mut tampered = execution_log.tables.clone();
+        tampered.event[0].inst.opcode = Opcode::Drop;
+        save_trace(&path, &compile_table, &tampered).unwrap();
+        assert!(matches!(
+            load_trace(&path, &compile_table),
+            Err(ArtifactError::InstructionMismatch { eid }) if eid == tampered.event[0].eid
+        ));
+
+        // the JSON tables of `trace --json` load as well
+        fs::write(&path, specs::codec::to_json(&execution_log.tables).unwrap()).unwrap();
+        assert_eq!(
+            load_trace(&path, &compile_table).unwrap().arguments(),
+            vec![3]
+        );
+        fs::write(&path, specs::codec::to_json(&tampered).unwrap()).unwrap();
+        assert!(matches!(
+            load_trace(&path, &compile_table),
+            Err(ArtifactError::InstructionMismatch { .. })
+        ));
+
+        fs::remove_file(&path).unwrap();
+    }
+}
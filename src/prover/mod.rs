@@ -0,0 +1,360 @@
+use std::fmt;
+
+use halo2_proofs::pairing::bn256::{Bn256, Fr, G1Affine};
+use halo2_proofs::plonk::{
+    create_proof, keygen_pk, keygen_vk, verify_proof, Error, ProvingKey, SingleVerifier,
+    VerifyingKey,
+};
+use halo2_proofs::poly::commitment::{Params, ParamsVerifier};
+use halo2_proofs::transcript::{Blake2bRead, Blake2bWrite, Challenge255};
+use rand::rngs::OsRng;
+use specs::checker::{check_execution, CheckError};
+use specs::segment::{initial_state, SegmentBoundary};
+use specs::types::Value;
+use specs::{CompileTable, ExecutionTable};
+
+use crate::circuits::event::CircuitOptions;
+use crate::circuits::image::image_id;
+use crate::circuits::io::instances_of;
+use crate::circuits::state::state_instances;
+use crate::circuits::wasm_circuit::{CircuitSizeError, WasmCircuit};
+
+pub mod artifact;
+pub mod segment;
+
+#[derive(Debug)]
+pub enum ProverError {
+    /// The tables do not fit in the `2^k` rows of the params or in the
+    /// advice columns of the circuit.
+    CircuitSize(CircuitSizeError),
+    /// The execution table breaks a rule of the circuits, see `check_execution`.
+    Check(CheckError),
+    Halo2(Error),
+}
+
+impl fmt::Display for ProverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProverError::CircuitSize(e) => write!(f, "{}", e),
+            ProverError::Check(e) => write!(f, "invalid execution: {}", e),
+            // halo2 errors only implement `Debug`
+            ProverError::Halo2(e) => write!(f, "halo2 error: {:?}", e),
+        }
+    }
+}
+
+impl std::error::Error for ProverError {}
+
+impl From<CheckError> for ProverError {
+    fn from(e: CheckError) -> Self {
+        ProverError::Check(e)
+    }
+}
+
+impl From<CircuitSizeError> for ProverError {
+    fn from(e: CircuitSizeError) -> Self {
+        ProverError::CircuitSize(e)
+    }
+}
+
+impl From<Error> for ProverError {
+    fn from(e: Error) -> Self {
+        ProverError::Halo2(e)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Proof {
+    /// Instance columns: the io instances, the image id and the segment state.
+    pub instances: Vec<Vec<Fr>>,
+    pub transcript: Vec<u8>,
+}
+
+/// What a verifier needs to know of an image, stored along with its verifying
+/// key so that proofs are checked without the module.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageInfo {
+    pub id: Fr,
+    pub first_fid: u16,
+    /// Digest of the module the image was compiled from, tells which program it is.
+    pub module_digest: [u8; 32],
+}
+
+impl ImageInfo {
+    pub fn of(compile_table: &CompileTable) -> ImageInfo {
+        ImageInfo {
+            id: image_id(compile_table),
+            first_fid: compile_table.first_fid(),
+            module_digest: compile_table.module_digest,
+        }
+    }
+}
+
+/// Instance columns a verifier expects for running the image compiled to
+/// `compile_table` with `arguments` and getting `returns`, `fuel` is given for
+/// keys generated with `CircuitOptions::fuel_metering`.
+pub fn public_inputs(
+    compile_table: &CompileTable,
+    arguments: &[Value],
+    returns: &[Value],
+    fuel: Option<u64>,
+) -> Vec<Vec<Fr>> {
+    image_public_inputs(&ImageInfo::of(compile_table), arguments, returns, fuel)
+}
+
+/// Like `public_inputs`, for verifiers which only know the image.
+pub fn image_public_inputs(
+    image: &ImageInfo,
+    arguments: &[Value],
+    returns: &[Value],
+    fuel: Option<u64>,
+) -> Vec<Vec<Fr>> {
+    let (start, start_stack) = initial_state(image.first_fid, arguments);
+    let boundary = SegmentBoundary {
+        start,
+        end: None,
+        start_stack,
+        end_stack: vec![],
+        returns: returns.to_vec(),
+        fuel: fuel.unwrap_or_default(),
+    };
+
+    vec![
+        instances_of(arguments, returns, fuel),
+        vec![image.id],
+        state_instances(image.first_fid, &boundary),
+    ]
+}
+
+/// KZG params for circuits of `2^k` rows.
+///
+/// The toxic waste is sampled locally, which is fine for tests and benchmarks only.
+pub fn setup_params(k: u32) -> Params<G1Affine> {
+    Params::<G1Affine>::unsafe_setup::<Bn256>(k)
+}
+
+/// Generates the proving key of a compiled program, the key only depends on the
+/// compile table and can be reused for every execution of it.
+///
+/// Proofs must be created and verified with the same `options`, usually
+/// `CircuitOptions::of(compile_table)`.
+pub fn setup(
+    params: &Params<G1Affine>,
+    options: &CircuitOptions,
+    compile_table: &CompileTable,
+) -> Result<ProvingKey<G1Affine>, ProverError> {
+    let circuit = WasmCircuit::<Fr>::new(
+        compile_table.clone(),
+        ExecutionTable::default(),
+        options.clone(),
+    )
+    .sized(params.k())?;
+
+    circuit.configured(|| {
+        let vk = keygen_vk(params, &circuit)?;
+        Ok(keygen_pk(params, vk, &circuit)?)
+    })
+}
+
+pub fn prove(
+    params: &Params<G1Affine>,
+    pk: &ProvingKey<G1Affine>,
+    options: &CircuitOptions,
+    compile_table: CompileTable,
+    execution_table: ExecutionTable,
+) -> Result<Proof, ProverError> {
+    check_execution(&compile_table, &execution_table)?;
+    let segment = execution_table.segments(usize::MAX).pop().unwrap();
+
+    segment::prove_segment(params, pk, options, compile_table, segment).map(|segment| segment.proof)
+}
+
+fn create(
+    params: &Params<G1Affine>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: WasmCircuit<Fr>,
+    instances: Vec<Vec<Fr>>,
+) -> Result<Proof, Error> {
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    circuit.configured(|| {
+        create_proof(
+            params,
+            pk,
+            std::slice::from_ref(&circuit),
+            &[&instances.iter().map(|x| &x[..]).collect::<Vec<_>>()[..]],
+            OsRng,
+            &mut transcript,
+        )
+    })?;
+
+    Ok(Proof {
+        instances,
+        transcript: transcript.finalize(),
+    })
+}
+
+pub fn verify(
+    params: &Params<G1Affine>,
+    vk: &VerifyingKey<G1Affine>,
+    proof: &Proof,
+) -> Result<(), Error> {
+    let public_inputs_size = proof.instances.iter().map(|x| x.len()).max().unwrap_or(0);
+    let params_verifier: ParamsVerifier<Bn256> = params.verifier(public_inputs_size)?;
+
+    let strategy = SingleVerifier::new(&params_verifier);
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof.transcript[..]);
+    verify_proof(
+        &params_verifier,
+        vk,
+        strategy,
+        &[&proof.instances.iter().map(|x| &x[..]).collect::<Vec<_>>()[..]],
+        &mut transcript,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuits::event::CircuitOptions;
+    use crate::circuits::image::image_id;
+    use crate::prover::{prove, public_inputs, setup, setup_params, verify, ProverError};
+    use crate::runtime::{WasmInterpreter, WasmRuntime};
+    use halo2_proofs::pairing::bn256::Fr;
+    use specs::types::Value;
+    use specs::CompileTable;
+
+    #[test]
+    fn test_prove_and_verify() {
+        let textual_repr = r#"
+                (module
+                    (func (export "test") (param i32)
+                      (local.get 0)
+                      (drop)
+                    )
+                   )
+                "#;
+
+        let compiler = WasmInterpreter::new();
+        let compiled_module = compiler.compile(textual_repr, "test").unwrap();
+
+        let options = CircuitOptions::of(&compiled_module.tables);
+        let params = setup_params(17);
+        let pk = setup(&params, &options, &compiled_module.tables).unwrap();
+        assert!(matches!(
+            setup(&setup_params(4), &options, &compiled_module.tables),
+            Err(ProverError::CircuitSize(_))
+        ));
+
+        // the proving key is reused for different executions
+        for arg in [1, 2] {
+            let execution_log = compiler
+                .run(&compiled_module, vec![Value::I32(arg)])
+                .unwrap();
+            assert!(matches!(
+                prove(
+                    &setup_params(4),
+                    &pk,
+                    &options,
+                    compiled_module.tables.clone(),
+                    execution_log.tables.clone(),
+                ),
+                Err(ProverError::CircuitSize(_))
+            ));
+            let proof = prove(
+                &params,
+                &pk,
+                &options,
+                compiled_module.tables.clone(),
+                execution_log.tables,
+            )
+            .unwrap();
+
+            assert_eq!(
+                proof.instances,
+                public_inputs(&compiled_module.tables, &[Value::I32(arg)], &[], None)
+            );
+            verify(&params, pk.get_vk(), &proof).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_prove_return_value() {
+        let textual_repr = r#"
+                (module
+                    (func (export "test") (param i32) (result i32)
+                      (local.get 0)
+                    )
+                   )
+                "#;
+
+        let compiler = WasmInterpreter::new();
+        let compiled_module = compiler.compile(textual_repr, "test").unwrap();
+        let execution_log = compiler.run(&compiled_module, vec![Value::I32(3)]).unwrap();
+
+        let options = CircuitOptions::of(&compiled_module.tables);
+        let params = setup_params(17);
+        let pk = setup(&params, &options, &compiled_module.tables).unwrap();
+        let mut proof = prove(
+            &params,
+            &pk,
+            &options,
+            compiled_module.tables.clone(),
+            execution_log.tables,
+        )
+        .unwrap();
+
+        assert_eq!(
+            proof.instances,
+            public_inputs(
+                &compiled_module.tables,
+                &[Value::I32(3)],
+                &[Value::I32(3)],
+                None
+            )
+        );
+        verify(&params, pk.get_vk(), &proof).unwrap();
+
+        // the proof does not hold for another result
+        proof.instances = public_inputs(
+            &compiled_module.tables,
+            &[Value::I32(3)],
+            &[Value::I32(4)],
+            None,
+        );
+        assert!(verify(&params, pk.get_vk(), &proof).is_err());
+    }
+
+    #[test]
+    fn test_verify_other_image() {
+        let compile = |textual_repr: &str| {
+            WasmInterpreter::new()
+                .compile(textual_repr, "test")
+                .unwrap()
+        };
+        let image =
+            compile(r#"(module (func (export "test") (param i32) (result i32) (local.get 0)))"#);
+        let other = compile(
+            r#"(module (func (export "test") (param i32) (result i32) (local.get 0) (drop) (local.get 0)))"#,
+        );
+        assert_ne!(image_id::<Fr>(&image.tables), image_id::<Fr>(&other.tables));
+
+        let params = setup_params(17);
+        let keys = |tables: &CompileTable| {
+            let options = CircuitOptions::of(tables);
+            let pk = setup(&params, &options, tables).unwrap();
+            (options, pk)
+        };
+        let (options, pk) = keys(&image.tables);
+        let (_, other_pk) = keys(&other.tables);
+
+        let execution_log = WasmInterpreter::new()
+            .run(&image, vec![Value::I32(3)])
+            .unwrap();
+        let mut proof = prove(&params, &pk, &options, image.tables, execution_log.tables).unwrap();
+        verify(&params, pk.get_vk(), &proof).unwrap();
+
+        // same arguments and returns, but the keys or the instances of the other image
+        assert!(verify(&params, other_pk.get_vk(), &proof).is_err());
+        proof.instances = public_inputs(&other.tables, &[Value::I32(3)], &[Value::I32(3)], None);
+        assert!(verify(&params, pk.get_vk(), &proof).is_err());
+    }
+}
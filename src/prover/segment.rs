@@ -0,0 +1,208 @@
+use halo2_proofs::pairing::bn256::{Fr, G1Affine};
+use halo2_proofs::plonk::{Error, ProvingKey, VerifyingKey};
+use halo2_proofs::poly::commitment::Params;
+use specs::segment::{initial_state, Segment, SegmentBoundary};
+use specs::types::Value;
+use specs::CompileTable;
+
+use crate::circuits::event::CircuitOptions;
+use crate::circuits::image::image_id;
+use crate::circuits::io::segment_instances_of;
+use crate::circuits::state::state_instances;
+use crate::circuits::wasm_circuit::WasmCircuit;
+use crate::prover::{create, verify, Proof, ProverError};
+
+/// Proof of a segment along with the boundary it was proven against.
+pub struct SegmentProof {
+    pub boundary: SegmentBoundary,
+    pub proof: Proof,
+}
+
+/// Public inputs of a segment of the image whose execution starts in `first_fid`.
+pub fn segment_public_inputs(
+    options: &CircuitOptions,
+    image_id: Fr,
+    first_fid: u16,
+    boundary: &SegmentBoundary,
+) -> Vec<Vec<Fr>> {
+    vec![
+        segment_instances_of(boundary, options.fuel_metering),
+        vec![image_id],
+        state_instances(first_fid, boundary),
+    ]
+}
+
+/// Segments share the proving key of the whole program and the options it was
+/// generated with, see `setup`.
+pub fn prove_segment(
+    params: &Params<G1Affine>,
+    pk: &ProvingKey<G1Affine>,
+    options: &CircuitOptions,
+    compile_table: CompileTable,
+    segment: Segment,
+) -> Result<SegmentProof, ProverError> {
+    let instances = segment_public_inputs(
+        options,
+        image_id(&compile_table),
+        compile_table.first_fid(),
+        &segment.boundary,
+    );
+    let boundary = segment.boundary.clone();
+    let circuit = WasmCircuit::<Fr>::from_segment(compile_table, segment, options.clone())
+        .sized(params.k())?;
+
+    Ok(SegmentProof {
+        boundary,
+        proof: create(params, pk, circuit, instances)?,
+    })
+}
+
+/// Verifies a whole execution of the image compiled to `compile_table` with
+/// `arguments`, given as consecutive segment proofs.
+///
+/// The first segment must start with the registers and the stack derived from
+/// the image and the arguments, and every following one must continue from
+/// the registers and stack its predecessor ended with. Only the last segment
+/// returns, its values are those of the execution and are returned.
+pub fn verify_segments(
+    params: &Params<G1Affine>,
+    vk: &VerifyingKey<G1Affine>,
+    options: &CircuitOptions,
+    compile_table: &CompileTable,
+    arguments: &[Value],
+    segments: &[SegmentProof],
+) -> Result<Vec<Value>, Error> {
+    let first = segments.first().ok_or(Error::InvalidInstances)?;
+    let last = segments.last().unwrap();
+    if last.boundary.end.is_some() {
+        return Err(Error::InvalidInstances);
+    }
+
+    let first_fid = compile_table.first_fid();
+    let (start, start_stack) = initial_state(first_fid, arguments);
+    if first.boundary.start != start || first.boundary.start_stack != start_stack {
+        return Err(Error::InvalidInstances);
+    }
+
+    let image_id = image_id(compile_table);
+    for (i, segment) in segments.iter().enumerate() {
+        if i > 0 && !segment.boundary.follows(&segments[i - 1].boundary) {
+            return Err(Error::InvalidInstances);
+        }
+        if segment.boundary.end.is_some() && !segment.boundary.returns.is_empty() {
+            return Err(Error::InvalidInstances);
+        }
+
+        let instances = segment_public_inputs(options, image_id, first_fid, &segment.boundary);
+        if segment.proof.instances != instances {
+            return Err(Error::InvalidInstances);
+        }
+
+        verify(params, vk, &segment.proof)?;
+    }
+
+    Ok(last.boundary.returns.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuits::event::CircuitOptions;
+    use crate::prover::segment::{prove_segment, verify_segments};
+    use crate::prover::{setup, setup_params};
+    use crate::runtime::{WasmInterpreter, WasmRuntime};
+    use crate::test::test_circuit_builder::run_test_segment;
+    use halo2_proofs::pairing::bn256::Fr;
+    use specs::types::Value;
+
+    #[test]
+    fn test_segments_chain() {
+        let textual_repr = r#"
+                (module
+                    (func (export "test") (param i32) (result i32)
+                      (local.get 0)
+                      (drop)
+                      (i32.const 0)
+                      (drop)
+                      (local.get 0)
+                    )
+                   )
+                "#;
+
+        let compiler = WasmInterpreter::new();
+        let compiled_module = compiler.compile(textual_repr, "test").unwrap();
+        let execution_log = compiler.run(&compiled_module, vec![Value::I32(3)]).unwrap();
+
+        let segments = execution_log.tables.segments(2);
+        assert!(segments.len() > 1);
+        for (prev, next) in segments.iter().zip(segments.iter().skip(1)) {
+            assert!(next.boundary.follows(&prev.boundary));
+            assert!(prev.boundary.returns.is_empty());
+        }
+        assert_eq!(
+            segments.last().unwrap().boundary.returns,
+            vec![Value::I32(3)]
+        );
+        let options = CircuitOptions::of(&compiled_module.tables);
+        for segment in segments.iter() {
+            run_test_segment::<Fr>(
+                compiled_module.tables.clone(),
+                segment.clone(),
+                options.clone(),
+            )
+            .unwrap();
+        }
+
+        // the end stack is committed exactly, no location can be added to it
+        let mut segment = segments[0].clone();
+        let mut extra = segment.boundary.end_stack[0].clone();
+        extra.offset = segment.boundary.end_stack.len() as u64;
+        segment.boundary.end_stack.push(extra);
+        assert!(
+            run_test_segment::<Fr>(compiled_module.tables.clone(), segment, options.clone())
+                .is_err()
+        );
+
+        let params = setup_params(17);
+        let pk = setup(&params, &options, &compiled_module.tables).unwrap();
+        let mut proofs = segments
+            .into_iter()
+            .map(|segment| {
+                prove_segment(
+                    &params,
+                    &pk,
+                    &options,
+                    compiled_module.tables.clone(),
+                    segment,
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        let verify = |arguments: &[Value], proofs: &[_]| {
+            verify_segments(
+                &params,
+                pk.get_vk(),
+                &options,
+                &compiled_module.tables,
+                arguments,
+                proofs,
+            )
+        };
+        assert_eq!(
+            verify(&[Value::I32(3)], &proofs).unwrap(),
+            vec![Value::I32(3)]
+        );
+        // the first segment is bound to the arguments of the verifier
+        assert!(verify(&[Value::I32(4)], &proofs).is_err());
+        assert!(verify(&[Value::I32(3)], &proofs[1..]).is_err());
+
+        // the returns are those the last proof was made for
+        let mut tampered = proofs.pop().unwrap();
+        tampered.boundary.returns = vec![Value::I32(4)];
+        proofs.push(tampered);
+        assert!(verify(&[Value::I32(3)], &proofs).is_err());
+
+        proofs.swap(0, 1);
+        assert!(verify(&[Value::I32(3)], &proofs).is_err());
+    }
+}
@@ -11,42 +11,116 @@ use specs::{
 use crate::runtime::wasmi_interpreter::WasmiRuntime;
 
 pub struct CompileOutcome<M> {
-    pub textual_repr: String,
+    /// Canonical encoding of the module, the one that is compiled and run.
+    pub binary: Vec<u8>,
+    pub entry: String,
     pub module: M,
     pub tables: CompileTable,
 }
 
 pub struct ExecutionOutcome {
     pub tables: ExecutionTable,
+    /// Results of the invoked function, the `keep_values` of its last `Return`.
+    pub returns: Vec<Value>,
+}
+
+/// Steps taken by `WasmRuntime::run` at most, every step adds a row to the event table.
+pub const DEFAULT_MAX_STEPS: usize = 1 << 20;
+
+/// Bounds of an execution, exceeding one fails the run before it is traced.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExecutionLimits {
+    pub max_steps: usize,
+    /// Fuel budget, see `OpcodeClass::fuel`, unmetered when `None`.
+    pub fuel: Option<u64>,
+}
+
+impl Default for ExecutionLimits {
+    fn default() -> Self {
+        ExecutionLimits {
+            max_steps: DEFAULT_MAX_STEPS,
+            fuel: None,
+        }
+    }
 }
 
 pub trait WasmRuntime {
     type Module;
 
     fn new() -> Self;
-    fn compile(&self, textual_repr: &str) -> Result<CompileOutcome<Self::Module>, CompileError>;
+    /// Compiles a module in the text format, see `compile_binary`.
+    fn compile(
+        &self,
+        textual_repr: &str,
+        entry: &str,
+    ) -> Result<CompileOutcome<Self::Module>, CompileError> {
+        let binary = wabt::wat2wasm(textual_repr).map_err(parse_error)?;
+        self.compile_binary(&binary, entry)
+    }
+    fn compile_binary(
+        &self,
+        binary: &[u8],
+        entry: &str,
+    ) -> Result<CompileOutcome<Self::Module>, CompileError>;
+    /// Runs with the default limits, see `run_with_limits`.
     fn run(
         &self,
         compile_outcome: &CompileOutcome<Self::Module>,
-        function_name: &str,
         args: Vec<Value>,
+    ) -> Result<ExecutionOutcome, ExecutionError> {
+        self.run_with_limits(compile_outcome, args, &ExecutionLimits::default())
+    }
+    fn run_with_limits(
+        &self,
+        compile_outcome: &CompileOutcome<Self::Module>,
+        args: Vec<Value>,
+        limits: &ExecutionLimits,
     ) -> Result<ExecutionOutcome, ExecutionError>;
 }
 
 pub type WasmInterpreter = WasmiRuntime;
 
+/// Position of wabt messages, formatted as `<file>:<line>:<column>: error: ...`.
+fn position_of(message: &str) -> Option<(u32, u32)> {
+    let mut parts = message.split(':').skip(1);
+    let line = parts.next()?.trim().parse().ok()?;
+    let column = parts.next()?.trim().parse().ok()?;
+
+    Some((line, column))
+}
+
+fn parse_error(err: wabt::Error) -> CompileError {
+    match err.kind() {
+        wabt::ErrorKind::Parse(message) | wabt::ErrorKind::ResolveNames(message) => {
+            CompileError::Parse {
+                message: message.clone(),
+                position: position_of(message),
+            }
+        }
+        wabt::ErrorKind::Validate(message) => CompileError::Validation(message.clone()),
+        kind => CompileError::Parse {
+            message: format!("{:?}", kind),
+            position: None,
+        },
+    }
+}
+
+/// Stack accesses of `event`, at absolute offsets: `event.sp` is the height of
+/// the stack before the event, so its top is at `sp - 1`.
 pub fn memory_event_of_step(event: &EventTableEntry, emid: &mut u64) -> Vec<MemoryTableEntry> {
     let eid = event.eid;
+    let sp = event.sp;
     let mmid = event.inst.mmid.into();
 
     match &event.step_info {
         StepInfo::BrIfNez { value, dst_pc } => mem_op_from_stack_only_step(
+            sp,
             eid,
             emid,
             mmid,
             VarType::I32,
             VarType::I32,
-            &[*value as u64],
+            &[*value as u32 as u64],
             &[],
         ),
         StepInfo::Return {
@@ -57,23 +131,50 @@ pub fn memory_event_of_step(event: &EventTableEntry, emid: &mut u64) -> Vec<Memo
         } => {
             assert_eq!(*drop as usize, drop_values.len());
             assert_eq!(keep.len(), keep_values.len());
-            mem_op_from_stack_only_step(
-                eid,
-                emid,
-                mmid,
-                VarType::I32,
-                VarType::I32,
-                drop_values.iter().map(|value| *value).collect::<Vec<_>>()[..]
-                    .try_into()
-                    .unwrap(),
-                keep_values.iter().map(|value| *value).collect::<Vec<_>>()[..]
-                    .try_into()
-                    .unwrap(),
-            )
-        }
-        StepInfo::Drop { value } => {
-            mem_op_from_stack_only_step(eid, emid, mmid, VarType::I32, VarType::I32, &[*value], &[])
+
+            // the kept values move down over the dropped ones
+            let kept = keep.len() as u64;
+            let reads = keep
+                .iter()
+                .zip(keep_values)
+                .enumerate()
+                .map(|(i, (vtype, value))| {
+                    (sp - kept + i as u64, AccessType::Read, *vtype, *value)
+                });
+            let writes = keep
+                .iter()
+                .zip(keep_values)
+                .enumerate()
+                .map(|(i, (vtype, value))| {
+                    (
+                        sp - *drop as u64 - kept + i as u64,
+                        AccessType::Write,
+                        *vtype,
+                        *value,
+                    )
+                });
+
+            reads
+                .chain(writes)
+                .map(|(offset, atype, vtype, value)| {
+                    let entry = MemoryTableEntry {
+                        eid,
+                        emid: *emid,
+                        mmid,
+                        offset,
+                        ltype: LocationType::Stack,
+                        atype,
+                        vtype: vtype.into(),
+                        value,
+                    };
+                    *emid = (*emid).checked_add(1).unwrap();
+
+                    entry
+                })
+                .collect()
         }
+        // the dropped value is left above the stack pointer, nothing is accessed
+        StepInfo::Drop { .. } => vec![],
         StepInfo::Call { index } => {
             vec![]
         }
@@ -86,7 +187,7 @@ pub fn memory_event_of_step(event: &EventTableEntry, emid: &mut u64) -> Vec<Memo
                 eid,
                 emid: *emid,
                 mmid,
-                offset: *depth as u64,
+                offset: sp - *depth as u64,
                 ltype: LocationType::Stack,
                 atype: AccessType::Read,
                 vtype: *vtype,
@@ -98,7 +199,7 @@ pub fn memory_event_of_step(event: &EventTableEntry, emid: &mut u64) -> Vec<Memo
                 eid,
                 emid: *emid,
                 mmid: mmid.into(),
-                offset: 0,
+                offset: sp,
                 ltype: LocationType::Stack,
                 atype: AccessType::Write,
                 vtype: *vtype,
@@ -109,36 +210,62 @@ pub fn memory_event_of_step(event: &EventTableEntry, emid: &mut u64) -> Vec<Memo
             vec![read, write]
         }
         StepInfo::I32Const { value } => mem_op_from_stack_only_step(
+            sp,
             eid,
             emid,
             mmid,
             VarType::I32,
             VarType::I32,
             &[],
-            &[*value as u64],
+            &[*value as u32 as u64],
         ),
         StepInfo::I32BinOp { left, right, value } => mem_op_from_stack_only_step(
+            sp,
             eid,
             emid,
             mmid,
             VarType::I32,
             VarType::I32,
-            &[*right as u64, *left as u64],
-            &[*value as u64],
+            &[*right as u32 as u64, *left as u32 as u64],
+            &[*value as u32 as u64],
         ),
         StepInfo::I32Comp { left, right, value } => mem_op_from_stack_only_step(
+            sp,
             eid,
             emid,
             mmid,
             VarType::I32,
             VarType::I32,
-            &[*right as u64, *left as u64],
+            &[*right as u32 as u64, *left as u32 as u64],
             &[*value as u64],
         ),
     }
 }
 
+pub fn memory_event_of_args(args: &Vec<Value>, emid: &mut u64) -> Vec<MemoryTableEntry> {
+    args.iter()
+        .enumerate()
+        .map(|(i, arg)| {
+            let init = MemoryTableEntry {
+                eid: 0,
+                emid: *emid,
+                mmid: 0,
+                offset: i as u64,
+                ltype: LocationType::Stack,
+                atype: AccessType::Init,
+                vtype: VarType::from(arg),
+                value: arg.raw(),
+            };
+            *emid = (*emid).checked_add(1).unwrap();
+
+            init
+        })
+        .collect()
+}
+
+/// Pops `pop_values`, the top of the stack first, then pushes `push_values`.
 fn mem_op_from_stack_only_step(
+    sp: u64,
     eid: u64,
     emid: &mut u64,
     mmid: u64,
@@ -154,7 +281,7 @@ fn mem_op_from_stack_only_step(
             eid,
             emid: *emid,
             mmid,
-            offset: i as u64,
+            offset: sp - 1 - i as u64,
             ltype: LocationType::Stack,
             atype: AccessType::Read,
             vtype: inputs_type,
@@ -168,7 +295,7 @@ fn mem_op_from_stack_only_step(
             eid,
             emid: *emid,
             mmid,
-            offset: i as u64,
+            offset: sp - pop_values.len() as u64 + i as u64,
             ltype: LocationType::Stack,
             atype: AccessType::Write,
             vtype: outputs_type,
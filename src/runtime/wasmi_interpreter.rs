@@ -1,22 +1,200 @@
-use crate::runtime::memory_event_of_step;
-use crate::runtime::{CompileOutcome, ExecutionOutcome, WasmRuntime};
+use crate::runtime::{memory_event_of_args, memory_event_of_step};
+use crate::runtime::{CompileOutcome, ExecutionLimits, ExecutionOutcome, WasmRuntime};
+use parity_wasm::elements::{ImportCountType, Instruction, Internal, Type};
 use specs::etable::EventTableEntry;
+use specs::itable::Opcode;
 use specs::mtable::MTable;
-use specs::types::{CompileError, ExecutionError, Value};
+use specs::types::{CompileError, ExecutionError, UnsupportedValueType, Value};
+use specs::CompileTable;
 use specs::ExecutionTable;
-use specs::{itable::InstructionTableEntry, CompileTable};
 use std::cell::RefCell;
 use std::rc::Rc;
-use wasmi::{ImportsBuilder, ModuleInstance, NopExternals, RuntimeValue};
+use wasmi::{
+    ImportsBuilder, ModuleInstance, ModuleRef, NopExternals, NotStartedModuleRef, RuntimeValue,
+};
 
 pub struct WasmiRuntime {}
 
+fn execution_error(err: wasmi::Error) -> ExecutionError {
+    match err {
+        wasmi::Error::Trap(trap) => ExecutionError::Trap {
+            kind: trap.to_string(),
+        },
+        wasmi::Error::Host(err) => ExecutionError::Host(err.to_string()),
+        err => ExecutionError::Invocation(err.to_string()),
+    }
+}
+
+/// Index of the exported function in the function index space, which is the fid used by the tracer.
+fn fid_of_export(module: &parity_wasm::elements::Module, entry: &str) -> Result<u16, CompileError> {
+    module
+        .export_section()
+        .and_then(|section| {
+            section
+                .entries()
+                .iter()
+                .find_map(|export| match export.internal() {
+                    Internal::Function(index) if export.field() == entry => Some(*index as u16),
+                    _ => None,
+                })
+        })
+        .ok_or_else(|| CompileError::MissingExport(entry.to_string()))
+}
+
+/// Rejects types and instructions which have no circuit, before the tracer meets them.
+fn check_functions(module: &parity_wasm::elements::Module) -> Result<(), CompileError> {
+    let imported = module.import_count(ImportCountType::Function);
+    let types = module
+        .type_section()
+        .map_or(&[][..], |section| section.types());
+    let functions = module
+        .function_section()
+        .map_or(&[][..], |section| section.entries());
+    let bodies = module
+        .code_section()
+        .map_or(&[][..], |section| section.bodies());
+
+    for (i, (func, body)) in functions.iter().zip(bodies).enumerate() {
+        let Type::Function(signature) = types
+            .get(func.type_ref() as usize)
+            .ok_or_else(|| CompileError::Validation(format!("unknown type {}", func.type_ref())))?;
+        let vtypes = signature
+            .params()
+            .iter()
+            .chain(signature.results())
+            .copied()
+            .chain(body.locals().iter().map(|local| local.value_type()));
+        for vtype in vtypes {
+            if let Err(UnsupportedValueType(vtype)) = specs::types::ValueType::try_from(vtype) {
+                return Err(CompileError::UnsupportedType {
+                    fid: (imported + i) as u16,
+                    vtype: vtype.to_string(),
+                });
+            }
+        }
+
+        for (iid, instruction) in body.code().elements().iter().enumerate() {
+            match instruction {
+                Instruction::GetLocal(_)
+                | Instruction::I32Const(_)
+                | Instruction::I64Const(_)
+                | Instruction::Drop
+                | Instruction::Return
+                | Instruction::End => (),
+                _ => {
+                    return Err(CompileError::UnsupportedInstruction {
+                        fid: (imported + i) as u16,
+                        iid: iid as u16,
+                        instruction: instruction.to_string(),
+                    })
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn instantiate(module: &wasmi::Module) -> Result<NotStartedModuleRef<'_>, wasmi::Error> {
+    ModuleInstance::new(module, &ImportsBuilder::default())
+}
+
 fn into_wasmi_value(v: Value) -> RuntimeValue {
     match v {
         Value::I32(v) => RuntimeValue::I32(v),
         Value::I64(v) => RuntimeValue::I64(v),
-        Value::U32(_) => todo!(),
-        Value::U64(_) => todo!(),
+        // wasm has no unsigned types, the bits are passed as they are
+        Value::U32(v) => RuntimeValue::I32(v as i32),
+        Value::U64(v) => RuntimeValue::I64(v as i64),
+    }
+}
+
+/// Checks `args` against the parameters of the export `entry`.
+fn check_arguments(
+    instance: &ModuleRef,
+    entry: &str,
+    args: &[Value],
+) -> Result<(), ExecutionError> {
+    let func = instance
+        .export_by_name(entry)
+        .and_then(|export| export.as_func().cloned())
+        .ok_or_else(|| ExecutionError::Invocation(format!("no function exported as {}", entry)))?;
+
+    let params = func.signature().params();
+    let matches = params.len() == args.len()
+        && params
+            .iter()
+            .zip(args)
+            .all(|(vtype, arg)| into_wasmi_value(arg.clone()).value_type() == *vtype);
+
+    if matches {
+        Ok(())
+    } else {
+        Err(ExecutionError::ArgumentMismatch {
+            expected: params
+                .iter()
+                .map(|vtype| from_wasmi_value(RuntimeValue::default(*vtype)).vtype())
+                .collect(),
+            found: args.iter().map(|arg| arg.vtype()).collect(),
+        })
+    }
+}
+
+fn check_limits(limits: &ExecutionLimits, steps: usize, fuel: u64) -> Result<(), ExecutionError> {
+    if steps > limits.max_steps {
+        return Err(ExecutionError::StepLimitExceeded {
+            limit: limits.max_steps,
+        });
+    }
+
+    match limits.fuel {
+        Some(budget) if fuel > budget => Err(ExecutionError::OutOfFuel { budget }),
+        _ => Ok(()),
+    }
+}
+
+/// Checks the limits against the steps the functions `fids` take when run one
+/// after the other, before any of them is traced.
+///
+/// `check_functions` rejects branches and calls, so a function runs its
+/// instructions in order up to its first return, and the trace is known ahead.
+fn check_limits_ahead(
+    tables: &CompileTable,
+    fids: impl Iterator<Item = u16>,
+    limits: &ExecutionLimits,
+) -> Result<(), ExecutionError> {
+    let mut steps = 0;
+    let mut fuel = 0;
+    for fid in fids {
+        let mut body = tables
+            .instructions
+            .iter()
+            .filter(|inst| inst.fid == fid)
+            .collect::<Vec<_>>();
+        body.sort_by_key(|inst| inst.iid);
+
+        for inst in body {
+            steps += 1;
+            fuel += inst.opcode.fuel();
+            check_limits(limits, steps, fuel)?;
+
+            if matches!(inst.opcode, Opcode::Return { .. }) {
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn from_wasmi_value(v: RuntimeValue) -> Value {
+    match v {
+        RuntimeValue::I32(v) => Value::I32(v),
+        RuntimeValue::I64(v) => Value::I64(v),
+        // floating point types are rejected by `check_functions`
+        RuntimeValue::F32(_) | RuntimeValue::F64(_) => {
+            unreachable!("floating point types are not supported")
+        }
     }
 }
 
@@ -27,19 +205,41 @@ impl WasmRuntime for WasmiRuntime {
         WasmiRuntime {}
     }
 
-    fn compile(&self, textual_repr: &str) -> Result<CompileOutcome<Self::Module>, CompileError> {
-        let binary = wabt::wat2wasm(&textual_repr).expect("failed to parse wat.");
-        let module = wasmi::Module::from_buffer(&binary).expect("failed to load wasm binary.");
+    fn compile_binary(
+        &self,
+        binary: &[u8],
+        entry: &str,
+    ) -> Result<CompileOutcome<Self::Module>, CompileError> {
+        let parsed: parity_wasm::elements::Module = parity_wasm::deserialize_buffer(binary)
+            .map_err(|err| CompileError::Validation(err.to_string()))?;
+
+        // the module is run from its canonical encoding, which the digest covers
+        let binary = parity_wasm::serialize(parsed.clone())
+            .map_err(|err| CompileError::Validation(err.to_string()))?;
+        // validated before the checks below look into its sections
+        let module = wasmi::Module::from_buffer(&binary)
+            .map_err(|err| CompileError::Validation(err.to_string()))?;
+
+        let fid_of_entry = fid_of_export(&parsed, entry)?;
+        let fid_of_start = parsed.start_section().map(|index| index as u16);
+        check_functions(&parsed)?;
 
-        let instance = ModuleInstance::new(&module, &ImportsBuilder::default())
-            .expect("failed to instantiate wasm module.")
-            .assert_no_start();
+        let module_digest = blake2b_simd::Params::new()
+            .hash_length(32)
+            .hash(&binary)
+            .as_bytes()
+            .try_into()
+            .unwrap();
+
+        let instance =
+            instantiate(&module).map_err(|err| CompileError::Instantiation(err.to_string()))?;
 
         let mut tracer = wasmi::tracer::Tracer::default();
-        tracer.register_module_instance(&instance);
+        tracer.register_module_instance(instance.not_started_instance());
 
         Ok(CompileOutcome {
-            textual_repr: textual_repr.to_string(),
+            binary,
+            entry: entry.to_string(),
             module,
             tables: CompileTable {
                 instructions: tracer
@@ -49,46 +249,94 @@ impl WasmRuntime for WasmiRuntime {
                     .map(|inst| inst.clone().into())
                     .collect(),
                 init_memory: vec![], // todo
+                fid_of_entry,
+                fid_of_start,
+                module_digest,
             },
         })
     }
 
-    fn run(
+    fn run_with_limits(
         &self,
         compile_outcome: &CompileOutcome<Self::Module>,
-        function_name: &str,
         args: Vec<Value>,
+        limits: &ExecutionLimits,
     ) -> Result<ExecutionOutcome, ExecutionError> {
-        let instance = ModuleInstance::new(&compile_outcome.module, &ImportsBuilder::default())
-            .expect("failed to instantiate wasm module")
-            .assert_no_start();
+        let instance = instantiate(&compile_outcome.module)
+            .map_err(|err| ExecutionError::Instantiation(err.to_string()))?;
+
+        check_arguments(
+            instance.not_started_instance(),
+            &compile_outcome.entry,
+            &args,
+        )?;
+
+        check_limits_ahead(
+            &compile_outcome.tables,
+            compile_outcome
+                .tables
+                .fid_of_start
+                .into_iter()
+                .chain([compile_outcome.tables.fid_of_entry]),
+            limits,
+        )?;
 
         let mut tracer = wasmi::tracer::Tracer::default();
-        tracer.register_module_instance(&instance);
+        tracer.register_module_instance(instance.not_started_instance());
         let tracer = Rc::new(RefCell::new(tracer));
 
-        assert_eq!(
-            instance
-                .invoke_export_trace(
-                    function_name,
-                    &args
-                        .into_iter()
-                        .map(|v| into_wasmi_value(v))
-                        .collect::<Vec<_>>(),
-                    &mut NopExternals,
-                    tracer.clone(),
-                )
-                .expect("failed to execute export"),
-            None,
+        let instance = instance
+            .run_start_tracer(&mut NopExternals, tracer.clone())
+            .map_err(|trap| execution_error(wasmi::Error::Trap(trap)))?;
+        let start_events = tracer.borrow().etable.0.len();
+
+        // the arguments are recorded with the types of the parameters
+        let args: Vec<_> = args.into_iter().map(into_wasmi_value).collect();
+        let arg_entries = memory_event_of_args(
+            &args.iter().cloned().map(from_wasmi_value).collect(),
+            &mut 1,
         );
 
+        let returns = instance
+            .invoke_export_trace(
+                &compile_outcome.entry,
+                &args,
+                &mut NopExternals,
+                tracer.clone(),
+            )
+            .map_err(execution_error)?
+            .into_iter()
+            .map(from_wasmi_value)
+            .collect();
+
+        // the proof starts with the arguments on the stack, see
+        // `specs::segment::initial_state`, and the start function runs above
+        // them while the tracer counts its stack from zero
+        let start_base_sp = args.len() as u64;
         let tracer = tracer.borrow();
-        let events: Vec<_> = tracer.etable.0.iter().map(|e| e.clone().into()).collect();
+        let events: Vec<EventTableEntry> = tracer
+            .etable
+            .0
+            .iter()
+            .enumerate()
+            .map(|(i, event)| {
+                let event: EventTableEntry = event.clone().into();
+                let base_sp = if i < start_events { start_base_sp } else { 0 };
+
+                EventTableEntry {
+                    sp: base_sp + event.sp,
+                    ..event
+                }
+            })
+            .collect();
         let mentries: Vec<_> = events
             .iter()
             .map(|e| memory_event_of_step(e, &mut 1))
             .collect();
-        let mentries = mentries.into_iter().flat_map(|x| x.into_iter()).collect();
+        let mentries = arg_entries
+            .into_iter()
+            .chain(mentries.into_iter().flat_map(|x| x.into_iter()))
+            .collect();
         let mut mtable = MTable::new(mentries);
         mtable.sort();
 
@@ -100,14 +348,241 @@ impl WasmRuntime for WasmiRuntime {
             .iter()
             .map(|jump| (*jump).clone().into())
             .collect::<Vec<_>>();
-        println!("{:?}", jumps);
 
-        Ok(ExecutionOutcome {
-            tables: ExecutionTable {
-                event: events,
-                memory: mtable,
-                jump: jumps,
-            },
-        })
+        let tables = ExecutionTable {
+            event: events,
+            memory: mtable,
+            jump: jumps,
+        };
+
+        Ok(ExecutionOutcome { returns, tables })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::checker::check_execution;
+    use specs::types::{CompileError, ExecutionError, Value, ValueType};
+
+    use crate::runtime::{ExecutionLimits, WasmInterpreter, WasmRuntime};
+
+    #[test]
+    fn test_compile_errors() {
+        let compiler = WasmInterpreter::new();
+
+        assert!(matches!(
+            compiler.compile("(module (func", "test"),
+            Err(CompileError::Parse { .. })
+        ));
+
+        let textual_repr = r#"
+                (module
+                    (func (export "test")
+                      (i32.const 1)
+                      (i32.const 2)
+                      (i32.add)
+                      (drop)
+                    )
+                   )
+                "#;
+        assert!(matches!(
+            compiler.compile(textual_repr, "missing"),
+            Err(CompileError::MissingExport(name)) if name == "missing"
+        ));
+        assert!(matches!(
+            compiler.compile(textual_repr, "test"),
+            Err(CompileError::UnsupportedInstruction { fid: 0, iid: 2, .. })
+        ));
+
+        // the exported function refers to a type missing from the type section
+        let binary = [
+            b"\0asm".as_slice(),
+            &[1, 0, 0, 0],
+            &[1, 1, 0],
+            &[3, 2, 1, 0],
+            &[7, 8, 1, 4, b't', b'e', b's', b't', 0, 0],
+            &[10, 4, 1, 2, 0, 0x0b],
+        ]
+        .concat();
+        assert!(matches!(
+            compiler.compile_binary(&binary, "test"),
+            Err(CompileError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_compile_binary() {
+        let textual_repr = r#"
+                (module
+                    (func (export "test") (param i32)
+                      (local.get 0)
+                      (drop)
+                    )
+                   )
+                "#;
+
+        let compiler = WasmInterpreter::new();
+        let from_text = compiler.compile(textual_repr, "test").unwrap();
+        let binary = wabt::wat2wasm(textual_repr).unwrap();
+        let from_binary = compiler.compile_binary(&binary, "test").unwrap();
+
+        assert_eq!(from_text.binary, from_binary.binary);
+        assert_eq!(
+            from_text.tables.module_digest,
+            from_binary.tables.module_digest
+        );
+        assert_eq!(
+            from_text.tables.instructions.len(),
+            from_binary.tables.instructions.len()
+        );
+    }
+
+    #[test]
+    fn test_run_returns() {
+        let textual_repr = r#"
+                (module
+                    (func (export "test") (result i32)
+                      (i32.const 42)
+                    )
+                   )
+                "#;
+
+        let compiler = WasmInterpreter::new();
+        let compiled_module = compiler.compile(textual_repr, "test").unwrap();
+        let execution_log = compiler.run(&compiled_module, vec![]).unwrap();
+
+        assert_eq!(execution_log.returns, vec![Value::I32(42)]);
+        assert_eq!(execution_log.tables.return_values(), vec![Value::I32(42)]);
+    }
+
+    #[test]
+    fn test_run_passes_checker() {
+        let textual_repr = r#"
+                (module
+                    (func (export "test") (param i32) (result i32)
+                      (i32.const 1)
+                      (drop)
+                      (local.get 0)
+                      (i32.const 2)
+                      (drop)
+                      (return)
+                    )
+                   )
+                "#;
+
+        let compiler = WasmInterpreter::new();
+        let compiled_module = compiler.compile(textual_repr, "test").unwrap();
+        let execution_log = compiler
+            .run(&compiled_module, vec![Value::I32(-3)])
+            .unwrap();
+
+        assert_eq!(execution_log.returns, vec![Value::I32(-3)]);
+        assert_eq!(
+            check_execution(&compiled_module.tables, &execution_log.tables),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_run_checks_arguments() {
+        let textual_repr = r#"
+                (module
+                    (func (export "test") (param i32) (result i32)
+                      (local.get 0)
+                    )
+                   )
+                "#;
+
+        let compiler = WasmInterpreter::new();
+        let compiled_module = compiler.compile(textual_repr, "test").unwrap();
+
+        let execution_log = compiler
+            .run(&compiled_module, vec![Value::U32(u32::MAX)])
+            .unwrap();
+        assert_eq!(execution_log.returns, vec![Value::I32(-1)]);
+
+        assert_eq!(
+            compiler.run(&compiled_module, vec![Value::I64(1)]).err(),
+            Some(ExecutionError::ArgumentMismatch {
+                expected: vec![ValueType::I32],
+                found: vec![ValueType::I64],
+            })
+        );
+        assert!(matches!(
+            compiler.run(&compiled_module, vec![]),
+            Err(ExecutionError::ArgumentMismatch { .. })
+        ));
+        assert!(matches!(
+            compiler.compile(r#"(module (func (export "test") (param f32)))"#, "test"),
+            Err(CompileError::UnsupportedType { fid: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_run_start() {
+        let textual_repr = r#"
+                (module
+                    (func $init
+                      (i32.const 1)
+                      (drop)
+                    )
+                    (func (export "test") (param i32) (result i32)
+                      (local.get 0)
+                    )
+                    (start $init)
+                   )
+                "#;
+
+        let compiler = WasmInterpreter::new();
+        let compiled_module = compiler.compile(textual_repr, "test").unwrap();
+        assert_eq!(compiled_module.tables.fid_of_start, Some(0));
+        assert_eq!(compiled_module.tables.first_fid(), 0);
+
+        let execution_log = compiler.run(&compiled_module, vec![Value::I32(7)]).unwrap();
+        let events = &execution_log.tables.event;
+
+        assert_eq!(execution_log.returns, vec![Value::I32(7)]);
+        assert_eq!(events[0].inst.fid, 0);
+        assert_eq!(events.last().unwrap().inst.fid, 1);
+        // the start function runs above the arguments and leaves the stack as it found it
+        assert_eq!(events[0].sp, 1);
+        assert_eq!(
+            check_execution(&compiled_module.tables, &execution_log.tables),
+            Ok(())
+        );
+        let first_of_entry = events.iter().position(|e| e.inst.fid == 1).unwrap();
+        assert_eq!(events[0].sp, events[first_of_entry].sp);
+    }
+
+    #[test]
+    fn test_run_limits() {
+        let textual_repr = r#"
+                (module
+                    (func (export "test")
+                      (i32.const 0)
+                      (drop)
+                    )
+                   )
+                "#;
+
+        let compiler = WasmInterpreter::new();
+        let compiled_module = compiler.compile(textual_repr, "test").unwrap();
+        let run = |max_steps, fuel| {
+            compiler.run_with_limits(
+                &compiled_module,
+                vec![],
+                &ExecutionLimits { max_steps, fuel },
+            )
+        };
+
+        assert_eq!(run(3, Some(4)).unwrap().tables.event.len(), 3);
+        assert_eq!(
+            run(2, None).err(),
+            Some(ExecutionError::StepLimitExceeded { limit: 2 })
+        );
+        assert_eq!(
+            run(3, Some(3)).err(),
+            Some(ExecutionError::OutOfFuel { budget: 3 })
+        );
     }
 }
@@ -1,2 +1,17 @@
-pub mod test_circuit;
-pub mod test_circuit_builder;
\ No newline at end of file
+pub mod test_circuit_builder;
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Path in the temporary directory that no other test, nor any concurrent run
+/// of the tests, uses.
+pub fn temp_path(name: &str) -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+    std::env::temp_dir().join(format!(
+        "zkswavm-{}-{}-{}",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed),
+        name
+    ))
+}
@@ -1,20 +1,47 @@
 use std::{cell::RefCell, rc::Rc};
 
-use halo2_proofs::{arithmetic::FieldExt, dev::MockProver, plonk::Error};
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    dev::{MockProver, VerifyFailure},
+};
+use specs::segment::Segment;
 use specs::{CompileTable, ExecutionTable};
 use wasmi::{ModuleRef, NopExternals};
 
-use crate::test::test_circuit::TestCircuit;
-
-const K: u32 = 17;
+use crate::circuits::event::CircuitOptions;
+use crate::circuits::image::image_id;
+use crate::circuits::io::segment_instances_of;
+use crate::circuits::state::state_instances;
+use crate::circuits::wasm_circuit::WasmCircuit;
 
 pub fn run_test_circuit<F: FieldExt>(
     compile_table: CompileTable,
     execution_table: ExecutionTable,
-) -> Result<(), Error> {
-    let circuit = TestCircuit::<F>::new(compile_table, execution_table);
+) -> Result<(), Vec<VerifyFailure>> {
+    let segment = execution_table.segments(usize::MAX).pop().unwrap();
+    let options = CircuitOptions::of(&compile_table);
+
+    run_test_segment::<F>(compile_table, segment, options)
+}
 
-    MockProver::run(K, &circuit, vec![])?;
+/// Checks the circuit of `segment` against the instances of its boundary with
+/// the mock prover, panics when the circuit does not fit or fails to synthesize.
+pub fn run_test_segment<F: FieldExt>(
+    compile_table: CompileTable,
+    segment: Segment,
+    options: CircuitOptions,
+) -> Result<(), Vec<VerifyFailure>> {
+    let instances = vec![
+        segment_instances_of::<F>(&segment.boundary, options.fuel_metering),
+        vec![image_id::<F>(&compile_table)],
+        state_instances::<F>(compile_table.first_fid(), &segment.boundary),
+    ];
+    let circuit = WasmCircuit::<F>::from_segment(compile_table, segment, options);
+    let k = circuit.min_k().unwrap();
+    let circuit = circuit.sized(k).unwrap();
 
-    Ok(())
+    circuit
+        .configured(|| MockProver::run(k, &circuit, instances))
+        .unwrap()
+        .verify()
 }